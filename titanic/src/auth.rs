@@ -1,22 +1,55 @@
 use crate::error::AppError;
+use async_trait::async_trait;
 use axum::http::HeaderMap;
 use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::info;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+use tokio::time::Instant;
+use tracing::{info, warn};
 
 use crate::config::Config;
+use crate::session::{self, SessionStoreHandle, SESSION_COOKIE_NAME};
+use crate::tokens::{self, Scope, TokenStoreHandle, TOKEN_PREFIX};
 
-#[derive(Debug, Serialize, Deserialize)]
+const JWKS_URL: &str =
+    "https://www.googleapis.com/robot/v1/metadata/x509/securetoken@system.gserviceaccount.com";
+
+/// Google doesn't always send caching headers; fall back to the same
+/// rotation window Firebase's own client libraries assume.
+const DEFAULT_JWKS_TTL: Duration = Duration::from_secs(3600);
+
+/// Refresh this long before expiry so the background task, not a request,
+/// pays for the fetch.
+const BACKGROUND_REFRESH_SLACK: Duration = Duration::from_secs(60);
+
+/// A permission that satisfies any [`crate::authz::RequirePermission`] check,
+/// granted to the dev-mode bypass user so local development isn't blocked by
+/// claims nobody configured yet.
+pub const PERMISSION_WILDCARD: &str = "*";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FirebaseUser {
     pub uid: String,
     pub email: String,
     pub email_verified: bool,
     pub name: Option<String>,
     pub picture: Option<String>,
+    /// `None` for a real Firebase ID token (fully privileged, as before);
+    /// `Some(scopes)` when authenticated via a personal access token, whose
+    /// privileges are limited to whatever it was issued with.
+    #[serde(default)]
+    pub scopes: Option<Vec<Scope>>,
+    /// Custom claim `roles` (if any), carried along for display/logging.
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Parsed out of the custom claim named by `Config::permission_claim`;
+    /// checked by [`crate::authz::RequirePermission`].
+    #[serde(default)]
+    pub permissions: HashSet<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,6 +66,11 @@ struct JwtPayload {
     name: Option<String>,
     picture: Option<String>,
     firebase: FirebaseClaims,
+    /// Firebase custom claims (e.g. `roles`, whatever `permission_claim`
+    /// names) ride alongside the standard fields rather than nested under
+    /// a key of their own.
+    #[serde(flatten)]
+    extra: HashMap<String, serde_json::Value>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -41,61 +79,250 @@ struct FirebaseClaims {
     identities: HashMap<String, Vec<String>>,
 }
 
-pub struct FirebaseAuth {
-    project_id: String,
-    client: Client,
-    public_keys: Arc<RwLock<HashMap<String, String>>>,
-    is_dev: bool,
+/// Cached JWKS keys plus when that cache should be considered stale.
+struct KeyCache {
+    keys: HashMap<String, String>,
+    expires_at: Instant,
 }
 
-impl FirebaseAuth {
-    pub fn new(config: &Config) -> Result<Self, AppError> {
-        let client = Client::new();
-        let public_keys = Arc::new(RwLock::new(HashMap::new()));
+impl KeyCache {
+    fn stale() -> Self {
+        KeyCache {
+            keys: HashMap::new(),
+            expires_at: Instant::now(),
+        }
+    }
+}
 
-        Ok(FirebaseAuth {
-            project_id: config.firebase_project_id.clone(),
+/// The JWKS fetch/cache machinery, split out from `FirebaseAuth` so the
+/// background refresh task can hold an `Arc` to just this part.
+struct KeyStore {
+    client: Client,
+    cache: RwLock<KeyCache>,
+    // Held across a refresh so concurrent cache misses don't all hit
+    // Google at once; whoever gets the lock second finds the cache already
+    // warm and returns immediately.
+    refresh_lock: Mutex<()>,
+}
+
+impl KeyStore {
+    fn new(client: Client) -> Self {
+        KeyStore {
             client,
-            public_keys,
-            is_dev: config.is_dev,
+            cache: RwLock::new(KeyCache::stale()),
+            refresh_lock: Mutex::new(()),
+        }
+    }
+
+    async fn get(&self, kid: &str) -> Result<String, AppError> {
+        {
+            let cache = self.cache.read().await;
+            if Instant::now() < cache.expires_at {
+                if let Some(key) = cache.keys.get(kid) {
+                    return Ok(key.clone());
+                }
+            }
+        }
+
+        self.refresh().await?;
+
+        let cache = self.cache.read().await;
+        cache.keys.get(kid).cloned().ok_or_else(|| {
+            AppError::AuthError("Key not found after refresh".to_string())
         })
     }
 
-    pub async fn verify_token(&self, headers: &HeaderMap) -> Result<FirebaseUser, AppError> {
-        // Bypass auth in development mode
-        if self.is_dev {
-            info!("DEV mode: Bypassing token verification");
-            return Ok(FirebaseUser {
-                uid: "dev-user".to_string(),
-                email: "dev@example.com".to_string(),
-                email_verified: true,
-                name: Some("Dev User".to_string()),
-                picture: None,
-            });
+    async fn refresh(&self) -> Result<(), AppError> {
+        let _guard = self.refresh_lock.lock().await;
+
+        // Someone else may have refreshed while we were waiting for the lock.
+        {
+            let cache = self.cache.read().await;
+            if Instant::now() < cache.expires_at && !cache.keys.is_empty() {
+                return Ok(());
+            }
         }
 
-        // Extract token from Authorization header
-        info!("Verifying token from headers...");
-        let auth_header = headers
-            .get("Authorization")
-            .and_then(|h| h.to_str().ok())
-            .ok_or_else(|| {
-                info!("Auth Error: No authorization header");
-                AppError::AuthError("No authorization header".to_string())
-            })?;
+        info!("Fetching JWKS from Google...");
+        let response = self
+            .client
+            .get(JWKS_URL)
+            .send()
+            .await
+            .map_err(|e| AppError::AuthError(format!("Failed to fetch public keys: {e}")))?;
 
-        if !auth_header.starts_with("Bearer ") {
-            info!("Auth Error: Invalid authorization header format");
-            return Err(AppError::AuthError(
-                "Invalid authorization header format".to_string(),
-            ));
+        if !response.status().is_success() {
+            return Err(AppError::AuthError(format!(
+                "Failed to fetch public keys from Firebase. Status: {}",
+                response.status()
+            )));
         }
 
-        let token = &auth_header[7..]; // Remove "Bearer " prefix
-        info!("Got bearer token, proceeding with verification.");
+        let ttl = parse_cache_ttl(response.headers()).unwrap_or(DEFAULT_JWKS_TTL);
 
-        // Verify the token
-        self.verify_firebase_token(token).await
+        let keys_text = response
+            .text()
+            .await
+            .map_err(|e| AppError::AuthError(format!("Failed to read response: {e}")))?;
+        let keys_map: HashMap<String, String> = serde_json::from_str(&keys_text)
+            .map_err(|e| AppError::AuthError(format!("Failed to parse public keys: {e}")))?;
+
+        let expires_at = Instant::now() + ttl;
+        {
+            let mut cache = self.cache.write().await;
+            cache.keys = keys_map;
+            cache.expires_at = expires_at;
+        }
+        info!("Cached JWKS, valid for {:?}", ttl);
+
+        Ok(())
+    }
+
+    /// Keeps the cache warm by refreshing a little before it expires, so
+    /// `get` almost never pays fetch latency on the request path.
+    async fn run_background_refresh(self: Arc<Self>) {
+        loop {
+            let expires_at = self.cache.read().await.expires_at;
+            let refresh_at = expires_at
+                .checked_sub(BACKGROUND_REFRESH_SLACK)
+                .unwrap_or_else(Instant::now);
+            let now = Instant::now();
+            if refresh_at > now {
+                tokio::time::sleep(refresh_at - now).await;
+            }
+
+            if let Err(e) = self.refresh().await {
+                warn!("Background JWKS refresh failed, will retry: {e}");
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+        }
+    }
+}
+
+/// Parse a TTL out of `Cache-Control: max-age=<n>` or, failing that,
+/// `Expires`, matching what the googleapis x509 endpoint actually sends so
+/// a rotated key isn't served stale for the rest of the process lifetime.
+///
+/// `pub(crate)` so [`crate::oidc`]'s JWKS cache, which polls a different
+/// Google endpoint for a differently-shaped key set, can reuse the same TTL
+/// parsing instead of duplicating it.
+pub(crate) fn parse_cache_ttl(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    if let Some(cache_control) = headers
+        .get(reqwest::header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+    {
+        for directive in cache_control.split(',') {
+            if let Some(value) = directive.trim().strip_prefix("max-age=") {
+                if let Ok(secs) = value.trim().parse::<u64>() {
+                    return Some(Duration::from_secs(secs));
+                }
+            }
+        }
+    }
+
+    let expires = headers
+        .get(reqwest::header::EXPIRES)
+        .and_then(|v| v.to_str().ok())?;
+    let expires_at = chrono::DateTime::parse_from_rfc2822(expires).ok()?;
+    (expires_at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+}
+
+/// Verifies whatever credential a request carries (a cookie, a bearer
+/// token, ...) and resolves it to a [`FirebaseUser`]. Pulled out as a trait
+/// so an [`AuthProviderChain`] can try several issuers in order — Firebase
+/// ID tokens, a plain Google OIDC token (see [`crate::oidc`]), the dev-mode
+/// bypass — without any handler caring which one actually resolved the
+/// request.
+#[async_trait]
+pub trait AuthProvider: Send + Sync {
+    async fn verify_token(&self, headers: &HeaderMap) -> Result<FirebaseUser, AppError>;
+}
+
+/// Providers tried in order until one resolves the request's credential;
+/// lives on `AppState::auth` so the dev bypass, the Firebase verifier and
+/// (if configured) the Google OIDC verifier can each be added or removed
+/// independently instead of being branches inside one verifier.
+pub type AuthProviderChain = Vec<Box<dyn AuthProvider>>;
+
+/// Run `providers` in order, returning the first one that resolves the
+/// credential. If every provider rejects it, returns the last provider's
+/// error (or a generic one if the chain is empty, which a correctly
+/// configured server never has).
+pub async fn verify_token(
+    providers: &AuthProviderChain,
+    headers: &HeaderMap,
+) -> Result<FirebaseUser, AppError> {
+    let mut last_err = None;
+    for provider in providers {
+        match provider.verify_token(headers).await {
+            Ok(user) => return Ok(user),
+            Err(e) => last_err = Some(e),
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| {
+        AppError::AuthError("No authentication providers configured".to_string())
+    }))
+}
+
+/// Stands in for real authentication in local development: unconditionally
+/// resolves to a fixed, fully-privileged user. Kept as its own provider
+/// (rather than a branch inside [`FirebaseAuth::verify_token`]) so enabling
+/// it is just putting it at the front of the chain when `Config::is_dev` is
+/// set, and disabling it is just not building it — `FirebaseAuth` itself no
+/// longer needs to know dev mode exists.
+pub struct DevBypassProvider;
+
+#[async_trait]
+impl AuthProvider for DevBypassProvider {
+    async fn verify_token(&self, _headers: &HeaderMap) -> Result<FirebaseUser, AppError> {
+        info!("DEV mode: bypassing token verification");
+        Ok(FirebaseUser {
+            uid: "dev-user".to_string(),
+            email: "dev@example.com".to_string(),
+            email_verified: true,
+            name: Some("Dev User".to_string()),
+            picture: None,
+            scopes: None,
+            roles: vec!["admin".to_string()],
+            permissions: HashSet::from([PERMISSION_WILDCARD.to_string()]),
+        })
+    }
+}
+
+pub struct FirebaseAuth {
+    project_id: String,
+    key_store: Arc<KeyStore>,
+    token_store: TokenStoreHandle,
+    session_store: SessionStoreHandle,
+    session_ttl: Duration,
+    permission_claim: String,
+}
+
+impl FirebaseAuth {
+    pub fn new(
+        config: &Config,
+        token_store: TokenStoreHandle,
+        session_store: SessionStoreHandle,
+        session_ttl: Duration,
+    ) -> Result<Self, AppError> {
+        let key_store = Arc::new(KeyStore::new(Client::new()));
+
+        let background_store = key_store.clone();
+        tokio::spawn(async move {
+            background_store.run_background_refresh().await;
+        });
+
+        Ok(FirebaseAuth {
+            project_id: config.firebase_project_id.clone(),
+            key_store,
+            token_store,
+            session_store,
+            session_ttl,
+            permission_claim: config.permission_claim.clone(),
+        })
     }
 
     async fn verify_firebase_token(&self, token: &str) -> Result<FirebaseUser, AppError> {
@@ -111,7 +338,7 @@ impl FirebaseAuth {
         info!("Found key ID (kid): {}", kid);
 
         // Get the public key
-        let public_key = self.get_public_key(&kid).await?;
+        let public_key = self.key_store.get(&kid).await?;
         info!("Successfully retrieved public key.");
 
         // Configure validation
@@ -141,82 +368,87 @@ impl FirebaseAuth {
             "Token verified successfully for user: {}",
             token_data.claims.email
         );
+
+        let roles = claim_string_list(&token_data.claims.extra, "roles");
+        let permissions = claim_string_list(&token_data.claims.extra, &self.permission_claim)
+            .into_iter()
+            .collect();
+
         Ok(FirebaseUser {
             uid: token_data.claims.user_id,
             email: token_data.claims.email,
             email_verified: token_data.claims.email_verified,
             name: token_data.claims.name,
             picture: token_data.claims.picture,
+            scopes: None,
+            roles,
+            permissions,
         })
     }
+}
 
-    async fn get_public_key(&self, kid: &str) -> Result<String, AppError> {
-        // Check if we have the key cached
-        {
-            let keys = self.public_keys.read().await;
-            if let Some(key) = keys.get(kid) {
-                info!("Found public key in cache for kid: {}", kid);
-                return Ok(key.clone());
-            }
-        }
-
-        // Fetch and cache all public keys from Firebase if cache is empty or key is not found
-        self.refresh_public_keys().await?;
+/// Pull a `Vec<String>` out of a custom claim, tolerating it being absent
+/// or not an array of strings rather than failing the whole token.
+fn claim_string_list(extra: &HashMap<String, serde_json::Value>, key: &str) -> Vec<String> {
+    extra
+        .get(key)
+        .and_then(|v| v.as_array())
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect()
+        })
+        .unwrap_or_default()
+}
 
-        // Try reading from cache again
-        {
-            let keys = self.public_keys.read().await;
-            if let Some(key) = keys.get(kid) {
-                info!("Found public key in cache for kid: {}", kid);
-                return Ok(key.clone());
+#[async_trait]
+impl AuthProvider for FirebaseAuth {
+    async fn verify_token(&self, headers: &HeaderMap) -> Result<FirebaseUser, AppError> {
+        // A session cookie resolves entirely in-process (no network call,
+        // no JWT re-validation); only fall back to `Authorization: Bearer`
+        // when there isn't one, or it doesn't resolve to anything live.
+        if let Some(session_id) = session::cookie_value(headers, SESSION_COOKIE_NAME) {
+            if let Some(user) =
+                session::resolve_and_refresh(&self.session_store, &session_id, self.session_ttl)
+                    .await?
+            {
+                info!("Resolved session cookie for user: {}", user.email);
+                return Ok(user);
             }
+            info!("Session cookie present but not valid; falling back to bearer token");
         }
 
-        // If still not found after refresh, it's an error
-        Err(AppError::AuthError(
-            "Key not found after refresh".to_string(),
-        ))
-    }
-
-    async fn refresh_public_keys(&self) -> Result<(), AppError> {
-        // Fetch public keys from Firebase
-        info!("Public key not in cache, fetching from Google...");
-        let url = "https://www.googleapis.com/robot/v1/metadata/x509/securetoken@system.gserviceaccount.com".to_string();
-
-        let response = self
-            .client
-            .get(&url)
-            .send()
-            .await
-            .map_err(|e| AppError::AuthError(format!("Failed to fetch public keys: {e}")))?;
+        // Extract token from Authorization header
+        info!("Verifying token from headers...");
+        let auth_header = headers
+            .get("Authorization")
+            .and_then(|h| h.to_str().ok())
+            .ok_or_else(|| {
+                info!("Auth Error: No authorization header");
+                AppError::AuthError("No authorization header".to_string())
+            })?;
 
-        if !response.status().is_success() {
-            info!(
-                "Failed to fetch public keys from Firebase. Status: {}",
-                response.status()
-            );
+        if !auth_header.starts_with("Bearer ") {
+            info!("Auth Error: Invalid authorization header format");
             return Err(AppError::AuthError(
-                "Failed to fetch public keys from Firebase".to_string(),
+                "Invalid authorization header format".to_string(),
             ));
         }
-        info!("Successfully fetched public keys from Google.");
-
-        let keys_text = response
-            .text()
-            .await
-            .map_err(|e| AppError::AuthError(format!("Failed to read response: {e}")))?;
 
-        // Parse the keys
-        let keys_map: HashMap<String, String> = serde_json::from_str(&keys_text)
-            .map_err(|e| AppError::AuthError(format!("Failed to parse public keys: {e}")))?;
+        let token = &auth_header[7..]; // Remove "Bearer " prefix
 
-        // Cache all the keys
-        {
-            let mut keys = self.public_keys.write().await;
-            *keys = keys_map;
-            info!("Cached all public keys from Google.");
+        // A personal access token is a flat opaque string, not a JWT; the
+        // `tit_` prefix lets us tell the two apart without first trying
+        // (and failing) to parse it as one.
+        if token.starts_with(TOKEN_PREFIX) {
+            info!("Got personal access token, proceeding with verification.");
+            return tokens::authenticate(&self.token_store, token).await;
         }
 
-        Ok(())
+        info!("Got bearer token, proceeding with verification.");
+
+        // Verify the token
+        self.verify_firebase_token(token).await
     }
 }
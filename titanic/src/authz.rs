@@ -0,0 +1,59 @@
+use crate::auth::{self, FirebaseUser, PERMISSION_WILDCARD};
+use crate::error::AppError;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::AppState;
+
+/// Compile-time marker for a permission a handler requires; implemented by
+/// the unit structs below so `RequirePermission<ManageSettingsPermission>`
+/// etc. can be used as an extractor. Mirrors `tokens::ScopeMarker`, but
+/// checks the `FirebaseUser::permissions` parsed from a Firebase custom
+/// claim rather than a personal access token's scopes.
+pub trait PermissionMarker {
+    const PERMISSION: &'static str;
+}
+
+/// Named `*Permission` (rather than plain `ManageSettings`) so it doesn't
+/// collide with `tokens::ManageSettings`, a `ScopeMarker` for the unrelated
+/// personal-access-token scope of the same name — the two are easy to
+/// mix up (`tokens.rs` importing this one once did, and didn't compile).
+pub struct ManageSettingsPermission;
+impl PermissionMarker for ManageSettingsPermission {
+    const PERMISSION: &'static str = "manage_settings";
+}
+
+/// Route-guard extractor: verifies the caller's bearer credential like
+/// `verify_token` does, then rejects with `403 Forbidden` if the resolved
+/// user's custom-claim permissions don't include `M::PERMISSION`.
+pub struct RequirePermission<M: PermissionMarker>(pub FirebaseUser, PhantomData<M>);
+
+impl<M: PermissionMarker + Send + Sync> FromRequestParts<Arc<AppState>> for RequirePermission<M> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        // `attach_sentry_context` already resolved this request's credential
+        // for Sentry attribution; reuse it instead of e.g. re-sliding a
+        // session cookie's expiry a second time for the same request.
+        let user = match parts.extensions.get::<FirebaseUser>() {
+            Some(user) => user.clone(),
+            None => auth::verify_token(&state.auth, &parts.headers).await?,
+        };
+
+        if !user.permissions.contains(M::PERMISSION)
+            && !user.permissions.contains(PERMISSION_WILDCARD)
+        {
+            return Err(AppError::Forbidden(format!(
+                "Missing required permission: {}",
+                M::PERMISSION
+            )));
+        }
+
+        Ok(RequirePermission(user, PhantomData))
+    }
+}
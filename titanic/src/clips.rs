@@ -0,0 +1,144 @@
+use crate::error::AppError;
+use crate::tokens::{ReadFiles, RequireScope};
+use crate::AppState;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::Response,
+};
+use bytes::Bytes;
+use futures_util::Stream;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tracing::info;
+
+/// `GET /api/clips/{folder}/{name}` — stream a stored clip back out,
+/// honoring an optional `Range` header so a browser `<video>` element can
+/// scrub/seek without Plex in the loop.
+pub async fn get_clip(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    RequireScope(_user, ..): RequireScope<ReadFiles>,
+    Path((folder, name)): Path<(String, String)>,
+) -> Result<Response, AppError> {
+    reject_path_traversal(&folder)?;
+    reject_path_traversal(&name)?;
+
+    let key = if folder == "Clips" {
+        format!("Clips/{name}")
+    } else {
+        format!("Clips/{folder}/{name}")
+    };
+
+    let requested_range = headers
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_range_header);
+
+    info!("Serving clip '{}' (range={:?})", key, requested_range);
+
+    let range_read = state.uploader.open_clip_range(&key, requested_range).await?;
+    let body_len = range_read.end - range_read.start + 1;
+    let is_partial = requested_range.is_some();
+
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, mime_for(&name))
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CONTENT_LENGTH, body_len.to_string());
+
+    if is_partial {
+        builder = builder.header(
+            header::CONTENT_RANGE,
+            format!(
+                "bytes {}-{}/{}",
+                range_read.start, range_read.end, range_read.total_len
+            ),
+        );
+    }
+    if let Some(last_modified) = range_read.last_modified {
+        builder = builder.header(header::LAST_MODIFIED, last_modified.to_rfc2822());
+    }
+
+    let status = if is_partial {
+        StatusCode::PARTIAL_CONTENT
+    } else {
+        StatusCode::OK
+    };
+
+    builder
+        .status(status)
+        .body(Body::from_stream(reader_to_stream(range_read.reader)))
+        .map_err(|e| AppError::InternalError(format!("Failed to build clip response: {e}")))
+}
+
+/// Reject a path segment that could escape the store root once concatenated
+/// into a key (`../..`, or an embedded `/` from a percent-encoded `%2F`
+/// that axum's `Path` extractor happily decodes before we see it). Mirrors
+/// `upload.rs`'s `sanitize_filename::sanitize` pass on the write side, but
+/// rejects outright here rather than silently rewriting, since this is a
+/// lookup and guessing what the caller "meant" isn't safe.
+fn reject_path_traversal(segment: &str) -> Result<(), AppError> {
+    if segment.is_empty()
+        || segment.contains('/')
+        || segment.contains('\\')
+        || segment.contains("..")
+    {
+        return Err(AppError::NotFound("Clip not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Parse a `Range: bytes=start-end` header into an inclusive `(start, end)`
+/// pair. Only the single-range form is supported; anything else (multiple
+/// ranges, a malformed header) is treated as "no range requested".
+fn parse_range_header(value: &str) -> Option<(u64, u64)> {
+    let spec = value.strip_prefix("bytes=")?;
+    let (start_s, end_s) = spec.split_once('-')?;
+    let start = start_s.trim().parse::<u64>().ok()?;
+    let end = if end_s.trim().is_empty() {
+        u64::MAX
+    } else {
+        end_s.trim().parse::<u64>().ok()?
+    };
+    Some((start, end))
+}
+
+fn mime_for(filename: &str) -> &'static str {
+    match filename
+        .rsplit('.')
+        .next()
+        .unwrap_or("")
+        .to_lowercase()
+        .as_str()
+    {
+        "mp4" | "m4v" => "video/mp4",
+        "mov" => "video/quicktime",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "avi" => "video/x-msvideo",
+        "wmv" => "video/x-ms-wmv",
+        "flv" => "video/x-flv",
+        "ts" => "video/mp2t",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Adapt an `AsyncRead` into the chunked `Stream` axum's `Body` wants.
+fn reader_to_stream(
+    reader: Pin<Box<dyn AsyncRead + Send>>,
+) -> impl Stream<Item = Result<Bytes, std::io::Error>> {
+    futures_util::stream::unfold(Some(reader), |state| async move {
+        let mut reader = state?;
+        let mut buf = vec![0u8; 64 * 1024];
+        match reader.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(Bytes::from(buf)), Some(reader)))
+            }
+            Err(e) => Some((Err(e), None)),
+        }
+    })
+}
@@ -1,3 +1,4 @@
+use crate::store::BlobStoreUri;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::env;
@@ -9,6 +10,19 @@ pub struct Config {
     pub plex_media_path: String,
     pub is_dev: bool,
     pub data_dir: String,
+    #[serde(skip, default = "default_blobstore_uri")]
+    pub blobstore_uri: BlobStoreUri,
+    pub ffprobe_enabled: bool,
+    pub ffprobe_timeout_secs: u64,
+    pub upload_timeout_secs: u64,
+    pub url_ingest_allowed_hosts: Vec<String>,
+    pub session_ttl_secs: u64,
+    pub permission_claim: String,
+    pub google_oidc_client_id: Option<String>,
+}
+
+fn default_blobstore_uri() -> BlobStoreUri {
+    BlobStoreUri::File("/downloads".into())
 }
 
 impl Config {
@@ -40,12 +54,78 @@ impl Config {
             }
         });
 
+        // Defaults to the legacy local-disk behavior (a `file://` URI
+        // pointed at `plex_media_path`) so existing deployments don't need
+        // to set anything to keep working.
+        let blobstore_raw = env::var("BLOBSTORE_URI")
+            .unwrap_or_else(|_| format!("file://{plex_media_path}"));
+        let blobstore_uri = BlobStoreUri::parse(&blobstore_raw)?;
+
+        // Hosts without ffmpeg installed can disable the ffprobe fallback
+        // and rely on the magic-byte check alone.
+        let ffprobe_enabled = env::var("FFPROBE_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .to_lowercase()
+            == "true";
+
+        let ffprobe_timeout_secs = env::var("FFPROBE_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(10);
+
+        // Server-side backstop on how long a single upload request may run,
+        // independent of (and generally longer than) any client-supplied
+        // `X-Upload-Deadline`.
+        let upload_timeout_secs = env::var("UPLOAD_TIMEOUT_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(3600);
+
+        // Hosts the `/api/upload-from-url` endpoint is allowed to fetch
+        // from. Empty by default, which disables the endpoint entirely
+        // rather than leaving it open to arbitrary SSRF targets.
+        let url_ingest_allowed_hosts = env::var("URL_INGEST_ALLOWED_HOSTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|h| h.trim().to_lowercase())
+            .filter(|h| !h.is_empty())
+            .collect();
+
+        // How long a session cookie stays valid without activity; each
+        // resolved request slides the expiry forward by this much.
+        let session_ttl_secs = env::var("SESSION_TTL_SECS")
+            .ok()
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(60 * 60 * 24 * 7);
+
+        // Name of the Firebase custom claim that carries a user's granted
+        // permissions, so deployments that already mint claims under a
+        // different key don't have to rename them to fit this server.
+        let permission_claim =
+            env::var("PERMISSION_CLAIM").unwrap_or_else(|_| "permissions".to_string());
+
+        // Adds a second `AuthProvider` to the chain that accepts a plain
+        // Google OIDC ID token (one Firebase Auth never wrapped) for this
+        // client ID. Left unset, the chain is just the dev bypass (in dev)
+        // plus Firebase.
+        let google_oidc_client_id = env::var("GOOGLE_OIDC_CLIENT_ID")
+            .ok()
+            .filter(|v| !v.is_empty());
+
         Ok(Config {
             bind_address,
             firebase_project_id,
             plex_media_path,
             is_dev,
             data_dir,
+            blobstore_uri,
+            ffprobe_enabled,
+            ffprobe_timeout_secs,
+            upload_timeout_secs,
+            url_ingest_allowed_hosts,
+            session_ttl_secs,
+            permission_claim,
+            google_oidc_client_id,
         })
     }
 }
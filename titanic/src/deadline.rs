@@ -0,0 +1,128 @@
+use crate::error::AppError;
+use crate::store::ByteStream;
+use crate::AppState;
+use axum::{
+    extract::{Request, State},
+    http::{header::HeaderName, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+};
+use futures_util::StreamExt;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+use tracing::warn;
+
+/// Clients may send this to cap an upload's lifetime below the server's
+/// default, e.g. a mobile client that knows it's about to be backgrounded.
+/// Its value is a unix-millis timestamp.
+pub static UPLOAD_DEADLINE_HEADER: HeaderName = HeaderName::from_static("x-upload-deadline");
+
+/// Resolve the effective deadline for a request: the server's configured
+/// maximum, clamped down further if the client asked for less via
+/// [`UPLOAD_DEADLINE_HEADER`]. Returns whether the client's deadline is the
+/// binding one, for status-code purposes.
+pub fn resolve_deadline(headers: &HeaderMap, max_duration: Duration) -> (Instant, bool) {
+    let now = Instant::now();
+    let server_deadline = now + max_duration;
+
+    let client_deadline = headers
+        .get(&UPLOAD_DEADLINE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .and_then(|millis| {
+            (UNIX_EPOCH + Duration::from_millis(millis))
+                .duration_since(SystemTime::now())
+                .ok()
+        })
+        .map(|remaining| now + remaining);
+
+    match client_deadline {
+        Some(client) if client < server_deadline => (client, true),
+        _ => (server_deadline, false),
+    }
+}
+
+/// Tower middleware enforcing a maximum request duration: races the inner
+/// handler against the resolved deadline and returns early if it loses.
+///
+/// The handler runs in its own spawned task rather than being raced
+/// directly, so losing the race only stops *waiting* on it — it doesn't
+/// drop (and so doesn't cancel) the handler future. That matters because
+/// the handler computes this same deadline a second time (via
+/// [`resolve_deadline`]) to drive [`wrap_with_deadline`], whose stream
+/// fails with `AppError::Timeout` once it elapses; `Store::save`'s cleanup
+/// of its partial temp file runs off the back of that error. If this
+/// middleware dropped the handler future itself, that cleanup path would
+/// never run and a stalled upload would leak its temp file every time.
+pub async fn enforce_deadline(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (deadline, is_client_deadline) = resolve_deadline(&headers, state.upload_timeout);
+
+    let mut handler = tokio::spawn(next.run(request));
+
+    tokio::select! {
+        result = &mut handler => {
+            match result {
+                Ok(response) => response,
+                Err(e) => {
+                    warn!("Request handler task panicked: {e}");
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        Json(json!({ "error": "Request handler panicked" })),
+                    )
+                        .into_response()
+                }
+            }
+        }
+        _ = tokio::time::sleep_until(deadline) => {
+            let status = if is_client_deadline {
+                StatusCode::REQUEST_TIMEOUT
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            };
+            warn!("Request exceeded its {} deadline", if is_client_deadline { "client-supplied" } else { "server" });
+            (
+                status,
+                Json(json!({ "error": "Request exceeded its allotted time" })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Wrap a byte stream so it starts failing with [`AppError::Timeout`] once
+/// `deadline` has passed, instead of writing forever. Combined with
+/// `Store::save`'s own cleanup of its temp file on error, this guarantees
+/// a deadline that elapses mid-upload doesn't leave a partial blob behind.
+pub fn wrap_with_deadline(inner: ByteStream, deadline: Instant) -> ByteStream {
+    Box::pin(futures_util::stream::unfold(
+        Some(inner),
+        move |state| async move {
+            let mut inner = state?;
+            if Instant::now() >= deadline {
+                return Some((
+                    Err(AppError::Timeout(
+                        "Upload exceeded its allotted time".to_string(),
+                    )),
+                    None,
+                ));
+            }
+            match tokio::time::timeout_at(deadline, inner.next()).await {
+                Ok(Some(item)) => Some((item, Some(inner))),
+                Ok(None) => None,
+                Err(_) => Some((
+                    Err(AppError::Timeout(
+                        "Upload exceeded its allotted time".to_string(),
+                    )),
+                    None,
+                )),
+            }
+        },
+    ))
+}
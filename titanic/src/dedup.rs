@@ -0,0 +1,97 @@
+use crate::error::AppError;
+use crate::store::ByteStream;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Mutex as AsyncMutex;
+use tracing::warn;
+
+/// Persisted hash -> canonical blob key / alias -> hash mapping backing
+/// content-addressed dedup. Loaded at startup the same way `Settings` is.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct BlobIndex {
+    /// content hash -> canonical key under `Clips/.blobs/...`
+    #[serde(default)]
+    pub blobs: HashMap<String, String>,
+    /// user-visible key (e.g. `Clips/folder/name.mp4`) -> content hash
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+}
+
+impl BlobIndex {
+    /// Load the index from a JSON file. Returns `Default` (an empty index)
+    /// when the file does not exist or cannot be parsed.
+    pub fn load(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Failed to parse blob index file: {e}; starting with an empty index");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Persist the index to a JSON file, creating parent dirs if needed.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Resolve the path to the blob index file for a given data directory.
+    pub fn file_path(data_dir: &str) -> PathBuf {
+        Path::new(data_dir).join("blob_index.json")
+    }
+}
+
+pub type BlobIndexHandle = Arc<AsyncMutex<BlobIndex>>;
+
+/// A small pointer object written in place of a real hardlink when the
+/// store can't make one (e.g. an S3 alias, or a hard link across
+/// filesystems on a local store).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlobPointer {
+    pub blob_key: String,
+}
+
+/// Canonical, content-addressed key for a blob, sharded two levels deep by
+/// hash prefix so no single directory grows unbounded:
+/// `Clips/.blobs/ab/cd/<hash>.<ext>`.
+pub fn blob_key_for_hash(hash: &str, ext: &str) -> String {
+    let shard_a = &hash[0..hash.len().min(2)];
+    let shard_b = &hash[hash.len().min(2)..hash.len().min(4)];
+    if ext.is_empty() {
+        format!("Clips/.blobs/{shard_a}/{shard_b}/{hash}")
+    } else {
+        format!("Clips/.blobs/{shard_a}/{shard_b}/{hash}.{ext}")
+    }
+}
+
+/// Wrap `inner` so every chunk is fed through a BLAKE3 hasher as it passes
+/// through unchanged; once the stream is exhausted, the hex digest is
+/// written into `hash_out`.
+pub fn hash_while_streaming(inner: ByteStream, hash_out: Arc<Mutex<Option<String>>>) -> ByteStream {
+    Box::pin(futures_util::stream::unfold(
+        (inner, blake3::Hasher::new(), hash_out),
+        |(mut inner, mut hasher, hash_out)| async move {
+            match inner.next().await {
+                Some(Ok(chunk)) => {
+                    hasher.update(&chunk);
+                    Some((Ok::<Bytes, AppError>(chunk), (inner, hasher, hash_out)))
+                }
+                Some(Err(e)) => Some((Err(e), (inner, hasher, hash_out))),
+                None => {
+                    let digest = hasher.finalize().to_hex().to_string();
+                    *hash_out.lock().unwrap() = Some(digest);
+                    None
+                }
+            }
+        },
+    ))
+}
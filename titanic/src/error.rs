@@ -13,6 +13,9 @@ pub enum AppError {
     #[error("Authentication error: {0}")]
     AuthError(String),
 
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
     #[error("Upload error: {0}")]
     UploadError(String),
 
@@ -21,15 +24,24 @@ pub enum AppError {
 
     #[error("Internal server error: {0}")]
     InternalError(String),
+
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Request timed out: {0}")]
+    Timeout(String),
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
         let (status, error_message) = match &self {
             AppError::AuthError(msg) => (StatusCode::UNAUTHORIZED, msg.clone()),
+            AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg.clone()),
             AppError::UploadError(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
             AppError::ConfigError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
             AppError::InternalError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg.clone()),
+            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            AppError::Timeout(msg) => (StatusCode::REQUEST_TIMEOUT, msg.clone()),
         };
 
         // Log the error before returning the response
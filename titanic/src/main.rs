@@ -6,15 +6,27 @@ use axum::{
     response::Json,
     routing::{get, post},
 };
+use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::net::TcpListener;
 use tracing::info;
 mod auth;
+mod authz;
+mod clips;
 mod config;
+mod deadline;
+mod dedup;
 mod error;
+mod ingest;
+mod oidc;
+mod session;
 mod settings;
+mod store;
+mod tmpdir;
+mod tokens;
 mod upload;
+mod validate;
 use axum::extract::multipart::MultipartError;
 
 use axum::http::{HeaderName, HeaderValue, Method};
@@ -23,8 +35,6 @@ use tower_http::{cors::CorsLayer, limit::RequestBodyLimitLayer};
 use auth::FirebaseAuth;
 use config::Config;
 use error::AppError;
-use tokio::fs::File;
-use tokio::io::AsyncWriteExt;
 use upload::VideoUploader;
 
 use crate::upload::SpaceInfo;
@@ -36,11 +46,11 @@ struct HealthResponse {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-struct UploadResponse {
-    message: String,
-    filename: String,
-    plex_path: String,
-    folder: Option<String>,
+pub(crate) struct UploadResponse {
+    pub(crate) message: String,
+    pub(crate) filename: String,
+    pub(crate) plex_path: String,
+    pub(crate) folder: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -56,10 +66,20 @@ struct FoldersResponse {
 }
 
 pub struct AppState {
-    pub auth: FirebaseAuth,
+    pub auth: auth::AuthProviderChain,
     pub uploader: VideoUploader,
     pub data_dir: String,
     pub sentry_guard: settings::SentryGuard,
+    pub upload_timeout: std::time::Duration,
+    pub http_client: reqwest::Client,
+    pub url_ingest_allowed_hosts: Vec<String>,
+    pub token_store: tokens::TokenStoreHandle,
+    pub token_store_path: std::path::PathBuf,
+    pub session_store: session::SessionStoreHandle,
+    pub session_ttl: std::time::Duration,
+    // Held only so the instance temp dir is removed on shutdown (its `Drop`
+    // impl does the cleanup); nothing reads this field directly.
+    _tmp_dir: Arc<tmpdir::TmpDir>,
 }
 
 impl From<MultipartError> for AppError {
@@ -68,7 +88,7 @@ impl From<MultipartError> for AppError {
     }
 }
 
-const CONTENT_LENGTH_LIMIT: usize = 10 * 1024 * 1024 * 1024; // 10GB
+pub(crate) const CONTENT_LENGTH_LIMIT: usize = 10 * 1024 * 1024 * 1024; // 10GB
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -91,15 +111,60 @@ async fn main() -> Result<()> {
         settings::init_sentry(&user_settings),
     ));
 
-    // Initialize Firebase authentication
-    let auth = FirebaseAuth::new(&config)?;
-    info!("Firebase authentication initialized");
-
-    // Initialize video uploader
-    let uploader = VideoUploader::new(&config.plex_media_path)?;
+    // Load the personal-access-token store (shared with FirebaseAuth, which
+    // needs it to resolve `tit_`-prefixed bearer credentials)
+    let token_store_path = tokens::TokenStore::file_path(&config.data_dir);
+    let token_store = Arc::new(tokio::sync::Mutex::new(tokens::TokenStore::load(
+        &token_store_path,
+    )));
+
+    // Load the session store (shared with FirebaseAuth, which resolves
+    // session cookies before falling back to bearer tokens)
+    let session_store: session::SessionStoreHandle =
+        Arc::new(session::FileSessionStore::new(&config.data_dir));
+    let session_ttl = std::time::Duration::from_secs(config.session_ttl_secs);
+
+    // Build the auth provider chain, tried in order until one resolves the
+    // request's credential: the dev bypass first (if enabled, so it never
+    // has to compete with a real check), then Firebase, then Google OIDC if
+    // a client ID was configured for it.
+    let mut auth: auth::AuthProviderChain = Vec::new();
+    if config.is_dev {
+        info!("IS_DEV set: adding the dev-mode auth bypass to the front of the provider chain");
+        auth.push(Box::new(auth::DevBypassProvider));
+    }
+    auth.push(Box::new(FirebaseAuth::new(
+        &config,
+        token_store.clone(),
+        session_store.clone(),
+        session_ttl,
+    )?));
+    if let Some(client_id) = config.google_oidc_client_id.clone() {
+        info!("GOOGLE_OIDC_CLIENT_ID set: adding the Google OIDC provider to the chain");
+        auth.push(Box::new(oidc::GoogleOidcProvider::new(client_id)));
+    }
+    info!("Authentication initialized with {} provider(s)", auth.len());
+
+    // Unique per-instance scratch dir for staging files (e.g. the ffprobe
+    // probe copy); also sweeps up anything a previous, uncleanly-stopped
+    // instance left behind.
+    let tmp_dir = Arc::new(tmpdir::TmpDir::create(&std::env::temp_dir())?);
+
+    // Initialize video uploader against the configured blob store
+    let store = store::build_store(&config.blobstore_uri).await?;
+    let blob_index_path = dedup::BlobIndex::file_path(&config.data_dir);
+    let blob_index = Arc::new(tokio::sync::Mutex::new(dedup::BlobIndex::load(&blob_index_path)));
+    let uploader = VideoUploader::new(
+        store,
+        config.ffprobe_enabled,
+        std::time::Duration::from_secs(config.ffprobe_timeout_secs),
+        blob_index,
+        blob_index_path,
+        tmp_dir.path().to_path_buf(),
+    );
     info!(
-        "Video uploader initialized with Plex path: {}",
-        config.plex_media_path
+        "Video uploader initialized with blob store: {:?}",
+        config.blobstore_uri
     );
 
     // Create shared state
@@ -109,6 +174,21 @@ async fn main() -> Result<()> {
         uploader,
         data_dir: config.data_dir,
         sentry_guard,
+        upload_timeout: std::time::Duration::from_secs(config.upload_timeout_secs),
+        // Redirects are followed manually in `ingest::fetch_allowed` so
+        // each hop can be re-checked against the ingest allowlist; a
+        // client-level auto-follow would let an allowlisted host redirect
+        // the fetch anywhere, bypassing the allowlist entirely.
+        http_client: reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("failed to build HTTP client"),
+        url_ingest_allowed_hosts: config.url_ingest_allowed_hosts,
+        token_store,
+        token_store_path,
+        session_store,
+        session_ttl,
+        _tmp_dir: tmp_dir,
     });
 
     // Configure CORS
@@ -132,20 +212,36 @@ async fn main() -> Result<()> {
             HeaderName::from_static("x-firebase-appcheck"),
             HeaderName::from_static("baggage"),
             HeaderName::from_static("sentry-trace"),
-        ]);
+        ])
+        .allow_credentials(true);
 
     // Build router
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/api/upload", post(upload_video))
+        .route("/api/upload-from-url", post(ingest::upload_from_url))
+        .route("/api/tokens", post(tokens::create_token))
+        .route(
+            "/api/session",
+            post(session::create_session).delete(session::delete_session),
+        )
         .route("/api/space", get(space_check))
         .route("/api/folders", get(list_folders))
+        .route("/api/clips/{folder}/{name}", get(clips::get_clip))
         .route("/", get(settings::settings_page))
         .route("/settings", get(settings::settings_page))
         .route("/api/settings", get(settings::get_settings).put(settings::put_settings))
         .layer(cors)
         .layer(DefaultBodyLimit::disable())
         .layer(RequestBodyLimitLayer::new(CONTENT_LENGTH_LIMIT))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            deadline::enforce_deadline,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            settings::attach_sentry_context,
+        ))
         .with_state(state);
 
     println!("Server starting on {bind_addr}");
@@ -167,6 +263,7 @@ async fn health_check() -> Json<HealthResponse> {
 async fn upload_video(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
+    tokens::RequireScope(user, ..): tokens::RequireScope<tokens::WriteFiles>,
     mut multipart: Multipart,
 ) -> Result<Json<UploadResponse>, AppError> {
     info!("Received an upload request");
@@ -175,41 +272,50 @@ async fn upload_video(
         info!("Header: {} = {:?}", key.as_str(), value);
     }
 
-    // Verify Firebase authentication
-    let user = state.auth.verify_token(&headers).await?;
     info!("Upload request from user: {}", user.email);
 
-    // Create a temporary file to stream the upload
-    let temp_dir = std::env::temp_dir();
-    let temp_file_path = temp_dir.join(format!(
-        "upload_{}_{}",
-        chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0),
-        "tempfile"
-    ));
-    let mut temp_file = File::create(&temp_file_path)
-        .await
-        .map_err(|e| AppError::InternalError(format!("Failed to create temp file: {e}")))?;
-
-    // Extract file and folder from multipart
+    // Extract file and folder from multipart. The `Store` abstraction
+    // streams the file straight through to its destination as it arrives,
+    // so the `folder` field must be sent before the `file` field (standard
+    // `FormData.append` ordering already does this).
     let mut filename: Option<String> = None;
     let mut folder: Option<String> = None;
-    let mut field_found = false;
+    let mut plex_path: Option<String> = None;
 
     info!("Starting multipart processing");
 
     while let Some(field) = multipart.next_field().await? {
         match field.name() {
             Some("file") => {
-                filename = field.file_name().map(|f| f.to_owned());
-                field_found = true;
-
-                let mut field_stream = field;
-                while let Some(chunk) = field_stream.chunk().await? {
-                    temp_file.write_all(&chunk).await.map_err(|e| {
-                        AppError::InternalError(format!("Failed to write to temp file: {e}"))
-                    })?;
+                let field_filename = field
+                    .file_name()
+                    .map(|f| f.to_owned())
+                    .ok_or_else(|| AppError::UploadError("No filename provided".to_string()))?;
+
+                if !is_valid_video_file(&field_filename) {
+                    return Err(AppError::UploadError("Invalid file type".to_string()));
                 }
-                // Don't break - continue processing other fields
+
+                info!(
+                    "About to save video: filename={}, folder={:?}",
+                    field_filename, folder
+                );
+                let stream: store::ByteStream =
+                    Box::pin(field.map(|chunk| chunk.map_err(AppError::from)));
+                let (upload_deadline, _) =
+                    deadline::resolve_deadline(&headers, state.upload_timeout);
+                plex_path = Some(
+                    state
+                        .uploader
+                        .upload_video(
+                            &field_filename,
+                            stream,
+                            folder.as_deref(),
+                            Some(upload_deadline),
+                        )
+                        .await?,
+                );
+                filename = Some(field_filename);
             }
             Some("folder") => {
                 if let Ok(text) = field.text().await {
@@ -228,39 +334,11 @@ async fn upload_video(
         }
     }
 
-    // Ensure the temp file is closed
-    drop(temp_file);
-
-    if !field_found {
-        // Clean up temp file if it was created but no field was found
-        let _ = tokio::fs::remove_file(&temp_file_path).await;
-        return Err(AppError::UploadError(
-            "No 'file' field in multipart request".to_string(),
-        ));
-    }
-
     let filename =
-        filename.ok_or_else(|| AppError::UploadError("No filename provided".to_string()))?;
-
-    // Validate file extension
-    if !is_valid_video_file(&filename) {
-        // Clean up the temp file before returning the error
-        let _ = tokio::fs::remove_file(&temp_file_path).await;
-        return Err(AppError::UploadError("Invalid file type".to_string()));
-    }
-
-    // Upload to Plex media directory by moving the temp file
-    info!(
-        "About to save video: filename={}, folder={:?}",
-        filename, folder
-    );
-    let plex_path = state
-        .uploader
-        .upload_video(&filename, &temp_file_path, folder.as_deref())
-        .await?;
-    info!("Upload completed, saved to: {}", plex_path);
-
-    // The temp file is moved by upload_video, so no need to delete it here.
+        filename.ok_or_else(|| AppError::UploadError("No 'file' field in multipart request".to_string()))?;
+    let plex_path = plex_path.ok_or_else(|| {
+        AppError::InternalError("Upload finished without a saved path".to_string())
+    })?;
 
     info!("Successfully saved {} to {}", filename, plex_path);
 
@@ -274,11 +352,8 @@ async fn upload_video(
 
 async fn space_check(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    tokens::RequireScope(_user, ..): tokens::RequireScope<tokens::ReadFiles>,
 ) -> Result<Json<SpaceInfo>, AppError> {
-    // Verify Firebase authentication
-    let _user = state.auth.verify_token(&headers).await?;
-
     let space_info = state.uploader.get_space_info().await?;
 
     Ok(Json(space_info))
@@ -286,17 +361,14 @@ async fn space_check(
 
 async fn list_folders(
     State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
+    tokens::RequireScope(_user, ..): tokens::RequireScope<tokens::ReadFiles>,
 ) -> Result<Json<FoldersResponse>, AppError> {
-    // Verify Firebase authentication
-    let _user = state.auth.verify_token(&headers).await?;
-
     let folders = state.uploader.list_folders().await?;
 
     Ok(Json(FoldersResponse { folders }))
 }
 
-fn is_valid_video_file(filename: &str) -> bool {
+pub(crate) fn is_valid_video_file(filename: &str) -> bool {
     let valid_extensions = [
         "mp4", "avi", "mov", "mkv", "wmv", "flv", "m4v", "avi", "webm", "ts",
     ];
@@ -0,0 +1,256 @@
+use crate::auth::{parse_cache_ttl, AuthProvider, FirebaseUser};
+use crate::error::AppError;
+use async_trait::async_trait;
+use axum::http::HeaderMap;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use reqwest::Client;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// Google's standard OIDC JWKS endpoint (a JSON Web Key Set), distinct from
+/// the Firebase-specific x509-cert endpoint `auth::KeyStore` polls: this one
+/// validates a plain Google-issued ID token (e.g. "Sign in with Google")
+/// rather than one Firebase Auth has re-wrapped with its own issuer/claims.
+const OIDC_JWKS_URL: &str = "https://www.googleapis.com/oauth2/v3/certs";
+
+const OIDC_ISSUERS: [&str; 2] = ["accounts.google.com", "https://accounts.google.com"];
+
+/// Google doesn't always send caching headers; fall back to the same
+/// rotation window assumed for the Firebase JWKS endpoint.
+const DEFAULT_JWKS_TTL: Duration = Duration::from_secs(3600);
+
+/// Refresh this long before expiry so the background task, not a request,
+/// pays for the fetch.
+const BACKGROUND_REFRESH_SLACK: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Deserialize)]
+struct Jwk {
+    kid: String,
+    n: String,
+    e: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+/// Cached JWKS keys plus when that cache should be considered stale.
+/// Mirrors `auth::KeyCache`, but keyed against an already-parsed
+/// `DecodingKey` since this endpoint hands back JWK `(n, e)` pairs rather
+/// than ready-to-use PEM certificates.
+struct KeyCache {
+    keys: HashMap<String, DecodingKey>,
+    expires_at: Instant,
+}
+
+impl KeyCache {
+    fn stale() -> Self {
+        KeyCache {
+            keys: HashMap::new(),
+            expires_at: Instant::now(),
+        }
+    }
+}
+
+/// The JWKS fetch/cache machinery for Google's OIDC certs endpoint, split
+/// out the same way `auth::KeyStore` is so the background refresh task can
+/// hold an `Arc` to just this part.
+struct KeyStore {
+    client: Client,
+    cache: RwLock<KeyCache>,
+    refresh_lock: Mutex<()>,
+}
+
+impl KeyStore {
+    fn new(client: Client) -> Self {
+        KeyStore {
+            client,
+            cache: RwLock::new(KeyCache::stale()),
+            refresh_lock: Mutex::new(()),
+        }
+    }
+
+    async fn get(&self, kid: &str) -> Result<DecodingKey, AppError> {
+        {
+            let cache = self.cache.read().await;
+            if Instant::now() < cache.expires_at {
+                if let Some(key) = cache.keys.get(kid) {
+                    return Ok(key.clone());
+                }
+            }
+        }
+
+        self.refresh().await?;
+
+        let cache = self.cache.read().await;
+        cache
+            .keys
+            .get(kid)
+            .cloned()
+            .ok_or_else(|| AppError::AuthError("Key not found after refresh".to_string()))
+    }
+
+    async fn refresh(&self) -> Result<(), AppError> {
+        let _guard = self.refresh_lock.lock().await;
+
+        // Someone else may have refreshed while we were waiting for the lock.
+        {
+            let cache = self.cache.read().await;
+            if Instant::now() < cache.expires_at && !cache.keys.is_empty() {
+                return Ok(());
+            }
+        }
+
+        info!("Fetching Google OIDC JWKS...");
+        let response = self
+            .client
+            .get(OIDC_JWKS_URL)
+            .send()
+            .await
+            .map_err(|e| AppError::AuthError(format!("Failed to fetch public keys: {e}")))?;
+
+        if !response.status().is_success() {
+            return Err(AppError::AuthError(format!(
+                "Failed to fetch public keys from Google. Status: {}",
+                response.status()
+            )));
+        }
+
+        let ttl = parse_cache_ttl(response.headers()).unwrap_or(DEFAULT_JWKS_TTL);
+
+        let jwk_set: JwkSet = response
+            .json()
+            .await
+            .map_err(|e| AppError::AuthError(format!("Failed to parse public keys: {e}")))?;
+
+        let mut keys = HashMap::new();
+        for jwk in jwk_set.keys {
+            match DecodingKey::from_rsa_components(&jwk.n, &jwk.e) {
+                Ok(key) => {
+                    keys.insert(jwk.kid, key);
+                }
+                Err(e) => warn!("Skipping unparseable Google OIDC JWK {}: {e}", jwk.kid),
+            }
+        }
+
+        let expires_at = Instant::now() + ttl;
+        {
+            let mut cache = self.cache.write().await;
+            cache.keys = keys;
+            cache.expires_at = expires_at;
+        }
+        info!("Cached Google OIDC JWKS, valid for {:?}", ttl);
+
+        Ok(())
+    }
+
+    /// Keeps the cache warm by refreshing a little before it expires, so
+    /// `get` almost never pays fetch latency on the request path.
+    async fn run_background_refresh(self: Arc<Self>) {
+        loop {
+            let expires_at = self.cache.read().await.expires_at;
+            let refresh_at = expires_at
+                .checked_sub(BACKGROUND_REFRESH_SLACK)
+                .unwrap_or_else(Instant::now);
+            let now = Instant::now();
+            if refresh_at > now {
+                tokio::time::sleep(refresh_at - now).await;
+            }
+
+            if let Err(e) = self.refresh().await {
+                warn!("Background Google OIDC JWKS refresh failed, will retry: {e}");
+                tokio::time::sleep(Duration::from_secs(30)).await;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct OidcClaims {
+    #[allow(dead_code)]
+    iss: String,
+    #[allow(dead_code)]
+    aud: String,
+    sub: String,
+    email: Option<String>,
+    email_verified: Option<bool>,
+    name: Option<String>,
+    picture: Option<String>,
+}
+
+/// Validates a plain Google-issued OIDC ID token (not one wrapped by
+/// Firebase Auth) against the client ID the server was configured with.
+/// Sits in [`crate::auth::AuthProviderChain`] alongside `FirebaseAuth`, only
+/// when `Config::google_oidc_client_id` is set.
+pub struct GoogleOidcProvider {
+    client_id: String,
+    key_store: Arc<KeyStore>,
+}
+
+impl GoogleOidcProvider {
+    pub fn new(client_id: String) -> Self {
+        let key_store = Arc::new(KeyStore::new(Client::new()));
+
+        let background_store = key_store.clone();
+        tokio::spawn(async move {
+            background_store.run_background_refresh().await;
+        });
+
+        GoogleOidcProvider {
+            client_id,
+            key_store,
+        }
+    }
+}
+
+#[async_trait]
+impl AuthProvider for GoogleOidcProvider {
+    async fn verify_token(&self, headers: &HeaderMap) -> Result<FirebaseUser, AppError> {
+        let auth_header = headers
+            .get("Authorization")
+            .and_then(|h| h.to_str().ok())
+            .ok_or_else(|| AppError::AuthError("No authorization header".to_string()))?;
+
+        let token = auth_header.strip_prefix("Bearer ").ok_or_else(|| {
+            AppError::AuthError("Invalid authorization header format".to_string())
+        })?;
+
+        let header = jsonwebtoken::decode_header(token)
+            .map_err(|e| AppError::AuthError(format!("Invalid token header: {e}")))?;
+        let kid = header
+            .kid
+            .ok_or_else(|| AppError::AuthError("No key ID in token".to_string()))?;
+
+        let key = self.key_store.get(&kid).await?;
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.set_audience(&[self.client_id.clone()]);
+        validation.set_issuer(&OIDC_ISSUERS);
+        validation.leeway = 60; // Allow for 60 seconds of clock skew
+
+        let token_data = decode::<OidcClaims>(token, &key, &validation)
+            .map_err(|e| AppError::AuthError(format!("Token verification failed: {e}")))?;
+
+        info!(
+            "Verified Google OIDC token for user: {}",
+            token_data.claims.sub
+        );
+
+        Ok(FirebaseUser {
+            uid: token_data.claims.sub,
+            email: token_data.claims.email.unwrap_or_default(),
+            email_verified: token_data.claims.email_verified.unwrap_or(false),
+            name: token_data.claims.name,
+            picture: token_data.claims.picture,
+            scopes: None,
+            roles: Vec::new(),
+            permissions: HashSet::new(),
+        })
+    }
+}
@@ -0,0 +1,235 @@
+use crate::auth::{self, FirebaseUser};
+use crate::error::AppError;
+use crate::AppState;
+use async_trait::async_trait;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+/// Name of the cookie set by `POST /api/session` and read back on every
+/// subsequent request so browsers stop replaying the Firebase ID token.
+pub const SESSION_COOKIE_NAME: &str = "titanic_session";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub user: FirebaseUser,
+    pub expiry: chrono::DateTime<chrono::Utc>,
+}
+
+/// Storage for active sessions. Mirrors `Store`'s pluggable-backend shape:
+/// an in-memory implementation for the common case, with room for other
+/// backends (a file-backed one is provided below) behind the same trait.
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    async fn get(&self, id: &str) -> Result<Option<SessionRecord>, AppError>;
+    async fn put(&self, id: &str, record: SessionRecord) -> Result<(), AppError>;
+    async fn delete(&self, id: &str) -> Result<(), AppError>;
+}
+
+/// Plain `RwLock<HashMap>` session store; lost on restart.
+pub struct InMemorySessionStore {
+    sessions: RwLock<HashMap<String, SessionRecord>>,
+}
+
+impl InMemorySessionStore {
+    pub fn new() -> Self {
+        InMemorySessionStore {
+            sessions: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for InMemorySessionStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl SessionStore for InMemorySessionStore {
+    async fn get(&self, id: &str) -> Result<Option<SessionRecord>, AppError> {
+        Ok(self.sessions.read().await.get(id).cloned())
+    }
+
+    async fn put(&self, id: &str, record: SessionRecord) -> Result<(), AppError> {
+        self.sessions.write().await.insert(id.to_string(), record);
+        Ok(())
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), AppError> {
+        self.sessions.write().await.remove(id);
+        Ok(())
+    }
+}
+
+/// Same in-memory map as [`InMemorySessionStore`], but write-through to a
+/// JSON file under `data_dir` so sessions survive a restart, the same way
+/// `Settings`/`BlobIndex`/`TokenStore` persist.
+pub struct FileSessionStore {
+    path: PathBuf,
+    inner: InMemorySessionStore,
+}
+
+impl FileSessionStore {
+    pub fn new(data_dir: &str) -> Self {
+        let path = Self::file_path(data_dir);
+        let sessions = match std::fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                warn!("Failed to parse sessions file: {e}; starting with no sessions");
+                HashMap::new()
+            }),
+            Err(_) => HashMap::new(),
+        };
+
+        FileSessionStore {
+            path,
+            inner: InMemorySessionStore {
+                sessions: RwLock::new(sessions),
+            },
+        }
+    }
+
+    pub fn file_path(data_dir: &str) -> PathBuf {
+        Path::new(data_dir).join("sessions.json")
+    }
+
+    fn persist(&self, sessions: &HashMap<String, SessionRecord>) -> anyhow::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(sessions)?;
+        std::fs::write(&self.path, json)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SessionStore for FileSessionStore {
+    async fn get(&self, id: &str) -> Result<Option<SessionRecord>, AppError> {
+        self.inner.get(id).await
+    }
+
+    async fn put(&self, id: &str, record: SessionRecord) -> Result<(), AppError> {
+        self.inner.put(id, record).await?;
+        let sessions = self.inner.sessions.read().await;
+        self.persist(&sessions)
+            .map_err(|e| AppError::InternalError(format!("Failed to persist sessions: {e}")))
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), AppError> {
+        self.inner.delete(id).await?;
+        let sessions = self.inner.sessions.read().await;
+        self.persist(&sessions)
+            .map_err(|e| AppError::InternalError(format!("Failed to persist sessions: {e}")))
+    }
+}
+
+pub type SessionStoreHandle = Arc<dyn SessionStore>;
+
+fn generate_session_id() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Resolve a session cookie to the `FirebaseUser` it belongs to, sliding
+/// its expiry forward by `ttl` on success (so an active browser session
+/// never has to re-authenticate). Returns `None` for an unknown or expired
+/// session rather than an error, so the caller can fall back to
+/// `Authorization: Bearer`.
+pub async fn resolve_and_refresh(
+    store: &SessionStoreHandle,
+    session_id: &str,
+    ttl: Duration,
+) -> Result<Option<FirebaseUser>, AppError> {
+    let Some(record) = store.get(session_id).await? else {
+        return Ok(None);
+    };
+
+    if chrono::Utc::now() > record.expiry {
+        let _ = store.delete(session_id).await;
+        return Ok(None);
+    }
+
+    let user = record.user.clone();
+    let refreshed = SessionRecord {
+        user: user.clone(),
+        expiry: chrono::Utc::now() + chrono::Duration::from_std(ttl).unwrap_or_default(),
+    };
+    store.put(session_id, refreshed).await?;
+
+    Ok(Some(user))
+}
+
+/// Pull a single cookie value out of a raw `Cookie` header.
+pub fn cookie_value(headers: &HeaderMap, name: &str) -> Option<String> {
+    let header_value = headers.get(header::COOKIE)?.to_str().ok()?;
+    header_value.split(';').find_map(|pair| {
+        let (key, value) = pair.trim().split_once('=')?;
+        (key == name).then(|| value.to_string())
+    })
+}
+
+fn set_cookie_header(name: &str, value: &str, max_age_secs: i64) -> String {
+    format!(
+        "{name}={value}; Path=/; Max-Age={max_age_secs}; HttpOnly; Secure; SameSite=Lax"
+    )
+}
+
+/// `POST /api/session` — verify the caller's Firebase ID token once and
+/// mint an opaque session cookie so the browser doesn't have to keep
+/// replaying (and we don't have to keep re-validating) the JWT.
+pub async fn create_session(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let user = auth::verify_token(&state.auth, &headers).await?;
+
+    let session_id = generate_session_id();
+    let expiry = chrono::Utc::now()
+        + chrono::Duration::from_std(state.session_ttl).unwrap_or_default();
+    state
+        .session_store
+        .put(&session_id, SessionRecord { user: user.clone(), expiry })
+        .await?;
+
+    info!("Created session for user: {}", user.email);
+
+    let cookie = set_cookie_header(
+        SESSION_COOKIE_NAME,
+        &session_id,
+        state.session_ttl.as_secs() as i64,
+    );
+
+    Ok((
+        StatusCode::OK,
+        [(header::SET_COOKIE, cookie)],
+        axum::Json(serde_json::json!({ "expires_at": expiry })),
+    )
+        .into_response())
+}
+
+/// `DELETE /api/session` — log out: drop the session server-side and clear
+/// the cookie.
+pub async fn delete_session(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    if let Some(session_id) = cookie_value(&headers, SESSION_COOKIE_NAME) {
+        state.session_store.delete(&session_id).await?;
+    }
+
+    let cleared_cookie = set_cookie_header(SESSION_COOKIE_NAME, "", 0);
+
+    Ok((StatusCode::NO_CONTENT, [(header::SET_COOKIE, cleared_cookie)]).into_response())
+}
@@ -1,8 +1,9 @@
 use axum::{
     Json,
-    extract::State,
-    http::{StatusCode, header},
-    response::IntoResponse,
+    extract::{Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
 };
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
@@ -10,6 +11,8 @@ use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::{info, warn};
 
+use crate::auth::{self, PERMISSION_WILDCARD};
+use crate::authz::{ManageSettingsPermission, PermissionMarker, RequirePermission};
 use crate::AppState;
 
 // ---------------------------------------------------------------------------
@@ -24,6 +27,15 @@ pub struct Settings {
     pub sentry_environment: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub sentry_traces_sample_rate: Option<f32>,
+    /// User uids to always trace at 100%, regardless of
+    /// `sentry_traces_sample_rate` — e.g. internal accounts used for
+    /// synthetic monitoring.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sentry_sample_always_uids: Vec<String>,
+    /// Route substrings (matched against the transaction name, e.g.
+    /// `/api/upload`) to always trace at 100%.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sentry_sample_always_routes: Vec<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub default_folder: Option<String>,
 }
@@ -63,6 +75,47 @@ impl Settings {
 
 pub type SentryGuard = Arc<Mutex<Option<sentry::ClientInitGuard>>>;
 
+tokio::task_local! {
+    /// Uid of the user the current request was resolved to, set by
+    /// [`attach_sentry_context`] for the duration of the handler so
+    /// [`traces_sampler`] can read it back without needing Sentry's own
+    /// custom-transaction-context plumbing.
+    static SENTRY_USER_UID: Option<String>;
+}
+
+/// A health check runs constantly and carries no useful signal; keep it
+/// off the trace volume entirely rather than letting it dominate whatever
+/// sample budget `sentry_traces_sample_rate` allows.
+const ALWAYS_DROP_ROUTES: &[&str] = &["/health"];
+
+/// Decide the sample rate for one transaction: drop health checks, always
+/// sample an allow-listed route or user uid, otherwise fall back to the
+/// configured flat rate.
+fn traces_sampler(ctx: &sentry::TransactionContext, settings: &Settings, default_rate: f32) -> f32 {
+    let name = ctx.name();
+
+    if ALWAYS_DROP_ROUTES.iter().any(|route| name.contains(route)) {
+        return 0.0;
+    }
+
+    if settings
+        .sentry_sample_always_routes
+        .iter()
+        .any(|route| name.contains(route.as_str()))
+    {
+        return 1.0;
+    }
+
+    let current_uid = SENTRY_USER_UID.try_with(Clone::clone).ok().flatten();
+    if let Some(uid) = current_uid {
+        if settings.sentry_sample_always_uids.contains(&uid) {
+            return 1.0;
+        }
+    }
+
+    default_rate
+}
+
 /// Initialise (or re-initialise) the Sentry SDK from the given settings,
 /// falling back to environment variables for any field not set.
 pub fn init_sentry(settings: &Settings) -> Option<sentry::ClientInitGuard> {
@@ -94,15 +147,18 @@ pub fn init_sentry(settings: &Settings) -> Option<sentry::ClientInitGuard> {
         })
         .unwrap_or(1.0);
 
-    info!("Initialising Sentry (environment={environment:?}, traces_sample_rate={traces_sample_rate})");
+    info!("Initialising Sentry (environment={environment:?}, default traces_sample_rate={traces_sample_rate})");
 
+    let sampler_settings = settings.clone();
     Some(sentry::init((
         dsn,
         sentry::ClientOptions {
             release: sentry::release_name!(),
             environment: environment.map(|v| v.into()),
             send_default_pii: true,
-            traces_sample_rate,
+            traces_sampler: Some(Arc::new(move |ctx: &sentry::TransactionContext| {
+                traces_sampler(ctx, &sampler_settings, traces_sample_rate)
+            })),
             ..Default::default()
         },
     )))
@@ -117,6 +173,58 @@ pub async fn reinit_sentry(settings: &Settings, guard: &SentryGuard) {
     *g = init_sentry(settings);
 }
 
+// ---------------------------------------------------------------------------
+// Middleware
+// ---------------------------------------------------------------------------
+
+/// Tower middleware: best-effort resolve the caller's credential and push
+/// the resulting `FirebaseUser` onto the Sentry scope, plus a breadcrumb
+/// for the request, so errors and traces are attributable to a user. Runs
+/// ahead of (and independently from) each route's own auth extractor, so a
+/// credential this can't resolve just leaves the request anonymous in
+/// Sentry rather than failing it here.
+///
+/// The resolved user is also stashed in the request's extensions so
+/// `RequireScope`/`RequirePermission` can pick it up instead of resolving
+/// the credential a second time — for a session-cookie-authenticated
+/// request that would otherwise mean `resolve_and_refresh` (and its
+/// `FileSessionStore::put` rewrite of the whole sessions file) running
+/// twice per request. A route whose extractor can't find one here (this
+/// middleware failed to resolve a credential at all) still falls back to
+/// resolving it itself, so auth failures are reported correctly either way.
+pub async fn attach_sentry_context(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+
+    let user = auth::verify_token(&state.auth, &headers).await.ok();
+
+    if let Some(user) = &user {
+        sentry::configure_scope(|scope| {
+            scope.set_user(Some(sentry::User {
+                id: Some(user.uid.clone()),
+                email: Some(user.email.clone()),
+                ..Default::default()
+            }));
+        });
+        request.extensions_mut().insert(user.clone());
+    }
+
+    sentry::add_breadcrumb(sentry::Breadcrumb {
+        category: Some("request".to_string()),
+        message: Some(format!("{method} {path}")),
+        level: sentry::Level::Info,
+        ..Default::default()
+    });
+
+    let uid = user.map(|u| u.uid);
+    SENTRY_USER_UID.scope(uid, next.run(request)).await
+}
+
 // ---------------------------------------------------------------------------
 // Route handlers
 // ---------------------------------------------------------------------------
@@ -132,17 +240,41 @@ pub async fn settings_page() -> impl IntoResponse {
     )
 }
 
-/// `GET /api/settings` — return current saved settings as JSON.
+/// `GET /api/settings` — return current saved settings as JSON. Open to any
+/// caller, authenticated or not (the settings page needs to read e.g.
+/// `default_folder` without necessarily holding `manage_settings`), but the
+/// always-sample allow-lists name real Firebase user uids, so those are
+/// stripped out unless the caller actually holds the permission that would
+/// let them change them via `PUT /api/settings` anyway.
 pub async fn get_settings(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
 ) -> Json<Settings> {
     let path = Settings::file_path(&state.data_dir);
-    Json(Settings::load(&path))
+    let mut settings = Settings::load(&path);
+
+    let can_manage_settings = auth::verify_token(&state.auth, &headers)
+        .await
+        .map(|user| {
+            user.permissions.contains(ManageSettingsPermission::PERMISSION)
+                || user.permissions.contains(PERMISSION_WILDCARD)
+        })
+        .unwrap_or(false);
+
+    if !can_manage_settings {
+        settings.sentry_sample_always_uids.clear();
+        settings.sentry_sample_always_routes.clear();
+    }
+
+    Json(settings)
 }
 
-/// `PUT /api/settings` — save settings and hot-reload Sentry.
+/// `PUT /api/settings` — save settings and hot-reload Sentry. Requires the
+/// `manage_settings` permission since this also controls where crash
+/// reports (potentially containing PII) get sent.
 pub async fn put_settings(
     State(state): State<Arc<AppState>>,
+    RequirePermission(_user, ..): RequirePermission<ManageSettingsPermission>,
     Json(payload): Json<Settings>,
 ) -> Result<Json<Settings>, (StatusCode, Json<serde_json::Value>)> {
     // Validate traces sample rate if provided.
@@ -157,6 +289,22 @@ pub async fn put_settings(
         }
     }
 
+    // Reject blank entries in the always-sample allow-lists the same way;
+    // an empty string would match every transaction name's `.contains("")`.
+    if payload
+        .sentry_sample_always_uids
+        .iter()
+        .chain(payload.sentry_sample_always_routes.iter())
+        .any(|entry| entry.trim().is_empty())
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "sentry_sample_always_uids/routes entries must not be blank"
+            })),
+        ));
+    }
+
     let path = Settings::file_path(&state.data_dir);
 
     payload.save(&path).map_err(|e| {
@@ -0,0 +1,794 @@
+use crate::dedup::BlobPointer;
+use crate::error::AppError;
+use crate::upload::SpaceInfo;
+use async_trait::async_trait;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures_util::{Stream, StreamExt};
+use std::path::PathBuf;
+use std::pin::Pin;
+use tokio::fs as tokio_fs;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tracing::info;
+
+/// A boxed stream of chunk results, as produced by an axum multipart field
+/// (or any other async byte source) that we want to hand straight to a
+/// [`Store`] without buffering it all into memory first.
+pub type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, AppError>> + Send>>;
+
+/// The result of opening a blob for a (possibly partial) read: a reader
+/// positioned at `start`, the blob's total size, the inclusive byte range
+/// actually being served, and its last-modified time if known.
+pub struct RangeRead {
+    pub reader: Pin<Box<dyn AsyncRead + Send>>,
+    pub total_len: u64,
+    pub start: u64,
+    pub end: u64,
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+/// Where uploaded video blobs actually live. `VideoUploader` talks to one of
+/// these instead of touching `std::fs` directly, so it can run against a
+/// local Plex-mounted disk or an S3-compatible bucket (MinIO, Wasabi, ...)
+/// without any change to the upload handler.
+#[async_trait]
+pub trait Store: Send + Sync {
+    /// Stream `stream` into the store under `key`, returning a
+    /// human-readable location (a filesystem path or an `s3://` URI) for
+    /// logging and API responses.
+    async fn save(&self, stream: ByteStream, key: &str) -> Result<String, AppError>;
+
+    /// Whether a blob already exists at `key`.
+    async fn exists(&self, key: &str) -> Result<bool, AppError>;
+
+    /// List the entries that live directly under `prefix` (used to drive
+    /// the folder-listing endpoint).
+    async fn list_prefix(&self, prefix: &str) -> Result<Vec<String>, AppError>;
+
+    /// Capacity information for the backing store.
+    async fn free_space(&self) -> Result<SpaceInfo, AppError>;
+
+    /// Remove the blob stored at `key`.
+    async fn delete(&self, key: &str) -> Result<(), AppError>;
+
+    /// Move a blob already in the store from `from` to `to`. Used to
+    /// promote a staged upload into its canonical content-addressed
+    /// location once its hash is known.
+    async fn rename(&self, from: &str, to: &str) -> Result<(), AppError>;
+
+    /// Create a user-visible `alias` that resolves to `canonical`'s
+    /// content, returning a human-readable location like [`save`]'s.
+    /// Implementations that support real hard links should use them;
+    /// others fall back to a small pointer object/file.
+    async fn link(&self, canonical: &str, alias: &str) -> Result<String, AppError>;
+
+    /// Open the blob at `key` for reading, resolving it to its real
+    /// content first if it turns out to be a [`BlobPointer`] alias. `range`
+    /// is an inclusive `(start, end)` byte range; `None` means the whole
+    /// object.
+    async fn open_range(&self, key: &str, range: Option<(u64, u64)>) -> Result<RangeRead, AppError>;
+}
+
+/// Parsed form of the `BLOBSTORE_URI` config value.
+#[derive(Debug, Clone)]
+pub enum BlobStoreUri {
+    File(PathBuf),
+    S3 {
+        bucket: String,
+        prefix: String,
+        endpoint: Option<String>,
+    },
+}
+
+impl BlobStoreUri {
+    /// Parse a `file:///path` or `s3://bucket/prefix?endpoint=...` URI.
+    pub fn parse(raw: &str) -> Result<Self, AppError> {
+        if let Some(rest) = raw.strip_prefix("file://") {
+            return Ok(BlobStoreUri::File(PathBuf::from(rest)));
+        }
+
+        if let Some(rest) = raw.strip_prefix("s3://") {
+            let (path_part, query) = rest.split_once('?').unwrap_or((rest, ""));
+            let mut parts = path_part.splitn(2, '/');
+            let bucket = parts.next().unwrap_or_default().to_string();
+            if bucket.is_empty() {
+                return Err(AppError::ConfigError(format!(
+                    "BLOBSTORE_URI '{raw}' is missing a bucket name"
+                )));
+            }
+            let prefix = parts.next().unwrap_or("").trim_matches('/').to_string();
+            let endpoint = query.split('&').find_map(|kv| {
+                let (k, v) = kv.split_once('=')?;
+                (k == "endpoint").then(|| v.to_string())
+            });
+            return Ok(BlobStoreUri::S3 {
+                bucket,
+                prefix,
+                endpoint,
+            });
+        }
+
+        Err(AppError::ConfigError(format!(
+            "Unrecognized BLOBSTORE_URI scheme in '{raw}'; expected file:// or s3://"
+        )))
+    }
+}
+
+/// Build the concrete `Store` implementation selected by `uri`.
+pub async fn build_store(uri: &BlobStoreUri) -> Result<Box<dyn Store>, AppError> {
+    match uri {
+        BlobStoreUri::File(path) => Ok(Box::new(FsStore::new(path.clone())?)),
+        BlobStoreUri::S3 {
+            bucket,
+            prefix,
+            endpoint,
+        } => Ok(Box::new(
+            S3Store::new(bucket.clone(), prefix.clone(), endpoint.clone()).await?,
+        )),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Filesystem store
+// ---------------------------------------------------------------------------
+
+pub struct FsStore {
+    root: PathBuf,
+}
+
+impl FsStore {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self, AppError> {
+        let root = root.into();
+
+        std::fs::create_dir_all(&root).map_err(|e| {
+            AppError::ConfigError(format!(
+                "Failed to create store root '{}': {e}",
+                root.display()
+            ))
+        })?;
+
+        if !root.is_dir() {
+            return Err(AppError::ConfigError(format!(
+                "Store root '{}' is not a directory",
+                root.display()
+            )));
+        }
+
+        Ok(FsStore { root })
+    }
+}
+
+#[async_trait]
+impl Store for FsStore {
+    async fn save(&self, mut stream: ByteStream, key: &str) -> Result<String, AppError> {
+        let target_path = self.root.join(key);
+        if let Some(parent) = target_path.parent() {
+            tokio_fs::create_dir_all(parent).await.map_err(|e| {
+                AppError::InternalError(format!(
+                    "Failed to create directory '{}': {e}",
+                    parent.display()
+                ))
+            })?;
+        }
+
+        // Write to a temp file alongside the target, then rename into place.
+        // The rename is atomic, so a crash mid-upload never leaves a reader
+        // looking at a half-written file where it expects a finished one.
+        let temp_path = target_path.with_file_name(format!(
+            ".{}.part-{}",
+            target_path
+                .file_name()
+                .and_then(|f| f.to_str())
+                .unwrap_or("upload"),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+        ));
+
+        let mut temp_file = tokio_fs::File::create(&temp_path).await.map_err(|e| {
+            AppError::InternalError(format!(
+                "Failed to create temp file '{}': {e}",
+                temp_path.display()
+            ))
+        })?;
+
+        // Any error here (a bad chunk, the client vanishing, a deadline
+        // elapsing mid-stream) must not leave the temp file behind.
+        let write_result: Result<(), AppError> = async {
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                temp_file.write_all(&chunk).await.map_err(|e| {
+                    AppError::InternalError(format!("Failed to write to temp file: {e}"))
+                })?;
+            }
+            temp_file
+                .flush()
+                .await
+                .map_err(|e| AppError::InternalError(format!("Failed to flush temp file: {e}")))
+        }
+        .await;
+        drop(temp_file);
+
+        if let Err(e) = write_result {
+            let _ = tokio_fs::remove_file(&temp_path).await;
+            return Err(e);
+        }
+
+        tokio_fs::rename(&temp_path, &target_path).await.map_err(|e| {
+            let _ = std::fs::remove_file(&temp_path);
+            AppError::InternalError(format!("Failed to move file into place: {e}"))
+        })?;
+
+        Ok(target_path.to_string_lossy().to_string())
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, AppError> {
+        Ok(tokio_fs::metadata(self.root.join(key)).await.is_ok())
+    }
+
+    async fn list_prefix(&self, prefix: &str) -> Result<Vec<String>, AppError> {
+        let dir = self.root.join(prefix);
+        std::fs::create_dir_all(&dir).map_err(|e| {
+            AppError::InternalError(format!("Failed to create directory '{}': {e}", dir.display()))
+        })?;
+
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(&dir).map_err(|e| {
+            AppError::InternalError(format!("Failed to read directory '{}': {e}", dir.display()))
+        })? {
+            let entry = entry.map_err(|e| {
+                AppError::InternalError(format!("Failed to read directory entry: {e}"))
+            })?;
+            let is_dir = entry.file_type().map_err(|e| {
+                AppError::InternalError(format!("Failed to get file type: {e}"))
+            })?.is_dir();
+            if is_dir {
+                if let Some(name) = entry.file_name().to_str() {
+                    // Internal shards like `.blobs` (the content-addressed
+                    // staging/dedup tree) aren't a real folder a caller
+                    // uploaded; don't surface them via `list_prefix`.
+                    if !name.starts_with('.') {
+                        entries.push(name.to_string());
+                    }
+                }
+            }
+        }
+        entries.sort();
+        Ok(entries)
+    }
+
+    async fn free_space(&self) -> Result<SpaceInfo, AppError> {
+        let path_str = self.root.to_str().ok_or_else(|| {
+            AppError::InternalError("Store root is not valid UTF-8".to_string())
+        })?;
+        let (total, used, free) = disk_space::get(path_str)?;
+        Ok(SpaceInfo { total, used, free })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        let path = self.root.join(key);
+        tokio_fs::remove_file(&path).await.map_err(|e| {
+            AppError::InternalError(format!("Failed to delete '{}': {e}", path.display()))
+        })
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), AppError> {
+        let from_path = self.root.join(from);
+        let to_path = self.root.join(to);
+        if let Some(parent) = to_path.parent() {
+            tokio_fs::create_dir_all(parent).await.map_err(|e| {
+                AppError::InternalError(format!(
+                    "Failed to create directory '{}': {e}",
+                    parent.display()
+                ))
+            })?;
+        }
+        tokio_fs::rename(&from_path, &to_path).await.map_err(|e| {
+            AppError::InternalError(format!(
+                "Failed to rename '{}' to '{}': {e}",
+                from_path.display(),
+                to_path.display()
+            ))
+        })
+    }
+
+    async fn link(&self, canonical: &str, alias: &str) -> Result<String, AppError> {
+        let canonical_path = self.root.join(canonical);
+        let alias_path = self.root.join(alias);
+        if let Some(parent) = alias_path.parent() {
+            tokio_fs::create_dir_all(parent).await.map_err(|e| {
+                AppError::InternalError(format!(
+                    "Failed to create directory '{}': {e}",
+                    parent.display()
+                ))
+            })?;
+        }
+        // A re-upload of the same name replaces whatever previously sat here.
+        let _ = tokio_fs::remove_file(&alias_path).await;
+
+        match tokio_fs::hard_link(&canonical_path, &alias_path).await {
+            Ok(()) => Ok(alias_path.to_string_lossy().to_string()),
+            Err(e) => {
+                info!(
+                    "Hard link from '{}' to '{}' failed ({e}); writing a pointer file instead",
+                    canonical_path.display(),
+                    alias_path.display()
+                );
+                let pointer = BlobPointer {
+                    blob_key: canonical.to_string(),
+                };
+                let json = serde_json::to_vec(&pointer).map_err(|e| {
+                    AppError::InternalError(format!("Failed to serialize blob pointer: {e}"))
+                })?;
+                tokio_fs::write(&alias_path, json).await.map_err(|e| {
+                    AppError::InternalError(format!(
+                        "Failed to write blob pointer '{}': {e}",
+                        alias_path.display()
+                    ))
+                })?;
+                Ok(alias_path.to_string_lossy().to_string())
+            }
+        }
+    }
+
+    async fn open_range(&self, key: &str, range: Option<(u64, u64)>) -> Result<RangeRead, AppError> {
+        let real_path = self.resolve_alias(key).await?;
+
+        let metadata = tokio_fs::metadata(&real_path).await.map_err(|e| {
+            AppError::NotFound(format!("Clip '{}' not found: {e}", real_path.display()))
+        })?;
+        let total_len = metadata.len();
+        let last_modified = metadata.modified().ok().map(DateTime::<Utc>::from);
+
+        let (start, end) = clamp_range(range, total_len);
+
+        let mut file = tokio_fs::File::open(&real_path).await.map_err(|e| {
+            AppError::InternalError(format!("Failed to open '{}': {e}", real_path.display()))
+        })?;
+        file.seek(std::io::SeekFrom::Start(start)).await.map_err(|e| {
+            AppError::InternalError(format!("Failed to seek '{}': {e}", real_path.display()))
+        })?;
+
+        Ok(RangeRead {
+            reader: Box::pin(file.take(end - start + 1)),
+            total_len,
+            start,
+            end,
+            last_modified,
+        })
+    }
+}
+
+impl FsStore {
+    /// Follow a pointer file one level deep. Pointer files are tiny JSON
+    /// documents, so anything above the threshold is assumed to be real
+    /// content and read as-is without paying for a full read.
+    async fn resolve_alias(&self, key: &str) -> Result<PathBuf, AppError> {
+        const MAX_POINTER_SIZE: u64 = 4096;
+
+        let path = self.root.join(key);
+        let metadata = tokio_fs::metadata(&path).await.map_err(|e| {
+            AppError::NotFound(format!("Clip '{}' not found: {e}", path.display()))
+        })?;
+
+        if metadata.len() <= MAX_POINTER_SIZE {
+            if let Ok(contents) = tokio_fs::read(&path).await {
+                if let Ok(pointer) = serde_json::from_slice::<BlobPointer>(&contents) {
+                    return Ok(self.root.join(&pointer.blob_key));
+                }
+            }
+        }
+
+        Ok(path)
+    }
+}
+
+/// Clamp a requested `(start, end)` range to `total_len`, defaulting to the
+/// whole object when no range was requested.
+fn clamp_range(range: Option<(u64, u64)>, total_len: u64) -> (u64, u64) {
+    let last_byte = total_len.saturating_sub(1);
+    match range {
+        Some((start, end)) => (start.min(last_byte), end.min(last_byte).max(start.min(last_byte))),
+        None => (0, last_byte),
+    }
+}
+
+mod disk_space {
+    use crate::error::AppError;
+    use std::process::Command;
+
+    pub fn get(path: &str) -> Result<(u64, u64, u64), AppError> {
+        let output = Command::new("df")
+            .arg("-k") // Use 1K blocks for POSIX compatibility
+            .arg(path)
+            .output()
+            .map_err(|e| {
+                AppError::InternalError(format!("Failed to execute 'df' command: {e}"))
+            })?;
+
+        if !output.status.success() {
+            return Err(AppError::InternalError(format!(
+                "'df' command failed with error: {}",
+                String::from_utf8_lossy(&output.stderr)
+            )));
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        let lines: Vec<&str> = output_str.trim().split('\n').collect();
+
+        if lines.len() < 2 {
+            return Err(AppError::InternalError(
+                "Unexpected 'df' output format".to_string(),
+            ));
+        }
+
+        let parts: Vec<&str> = lines[1].split_whitespace().collect();
+        if parts.len() < 4 {
+            return Err(AppError::InternalError(
+                "Unexpected 'df' output format on value line".to_string(),
+            ));
+        }
+
+        let total = parts[1]
+            .parse::<u64>()
+            .map_err(|_| AppError::InternalError("Failed to parse total space".to_string()))?
+            * 1024; // Convert from 1K-blocks to bytes
+        let used = parts[2]
+            .parse::<u64>()
+            .map_err(|_| AppError::InternalError("Failed to parse used space".to_string()))?
+            * 1024;
+        let free = parts[3]
+            .parse::<u64>()
+            .map_err(|_| AppError::InternalError("Failed to parse free space".to_string()))?
+            * 1024;
+
+        Ok((total, used, free))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// S3-compatible store
+// ---------------------------------------------------------------------------
+
+/// Parts below this size (except the last) are rejected by S3's multipart
+/// API, so we buffer chunks up to this before flushing a part.
+const MIN_PART_SIZE: usize = 8 * 1024 * 1024;
+
+pub struct S3Store {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+    prefix: String,
+}
+
+impl S3Store {
+    pub async fn new(
+        bucket: String,
+        prefix: String,
+        endpoint: Option<String>,
+    ) -> Result<Self, AppError> {
+        let mut loader = aws_config::from_env();
+        if let Some(endpoint) = &endpoint {
+            loader = loader.endpoint_url(endpoint);
+        }
+        let shared_config = loader.load().await;
+        let s3_config = aws_sdk_s3::config::Builder::from(&shared_config)
+            // MinIO/Wasabi expect bucket-in-path rather than vhost-style URLs.
+            .force_path_style(true)
+            .build();
+
+        info!("Using S3 blob store: bucket={bucket}, prefix={prefix}, endpoint={endpoint:?}");
+
+        Ok(S3Store {
+            client: aws_sdk_s3::Client::from_conf(s3_config),
+            bucket,
+            prefix,
+        })
+    }
+
+    fn full_key(&self, key: &str) -> String {
+        if self.prefix.is_empty() {
+            key.trim_start_matches('/').to_string()
+        } else {
+            format!("{}/{}", self.prefix.trim_matches('/'), key.trim_start_matches('/'))
+        }
+    }
+
+    async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Vec<u8>,
+    ) -> Result<aws_sdk_s3::types::CompletedPart, AppError> {
+        let resp = self
+            .client
+            .upload_part()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(aws_sdk_s3::primitives::ByteStream::from(data))
+            .send()
+            .await
+            .map_err(|e| {
+                AppError::InternalError(format!("Failed to upload S3 part {part_number}: {e}"))
+            })?;
+
+        Ok(aws_sdk_s3::types::CompletedPart::builder()
+            .e_tag(resp.e_tag().unwrap_or_default())
+            .part_number(part_number)
+            .build())
+    }
+}
+
+#[async_trait]
+impl Store for S3Store {
+    async fn save(&self, mut stream: ByteStream, key: &str) -> Result<String, AppError> {
+        let full_key = self.full_key(key);
+
+        let create = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&full_key)
+            .send()
+            .await
+            .map_err(|e| {
+                AppError::InternalError(format!("Failed to start S3 multipart upload: {e}"))
+            })?;
+        let upload_id = create
+            .upload_id()
+            .ok_or_else(|| AppError::InternalError("S3 did not return an upload id".to_string()))?
+            .to_string();
+
+        let mut buffer: Vec<u8> = Vec::with_capacity(MIN_PART_SIZE);
+        let mut parts = Vec::new();
+        let mut part_number: i32 = 1;
+
+        let upload_result: Result<(), AppError> = async {
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk?;
+                buffer.extend_from_slice(&chunk);
+                if buffer.len() >= MIN_PART_SIZE {
+                    let part = self
+                        .upload_part(&full_key, &upload_id, part_number, std::mem::take(&mut buffer))
+                        .await?;
+                    parts.push(part);
+                    part_number += 1;
+                }
+            }
+            // S3 requires at least one part even for an empty object.
+            if !buffer.is_empty() || parts.is_empty() {
+                let part = self
+                    .upload_part(&full_key, &upload_id, part_number, std::mem::take(&mut buffer))
+                    .await?;
+                parts.push(part);
+            }
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = upload_result {
+            let _ = self
+                .client
+                .abort_multipart_upload()
+                .bucket(&self.bucket)
+                .key(&full_key)
+                .upload_id(&upload_id)
+                .send()
+                .await;
+            return Err(e);
+        }
+
+        let completed = aws_sdk_s3::types::CompletedMultipartUpload::builder()
+            .set_parts(Some(parts))
+            .build();
+
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(&full_key)
+            .upload_id(&upload_id)
+            .multipart_upload(completed)
+            .send()
+            .await
+            .map_err(|e| {
+                AppError::InternalError(format!("Failed to complete S3 multipart upload: {e}"))
+            })?;
+
+        Ok(format!("s3://{}/{}", self.bucket, full_key))
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool, AppError> {
+        let full_key = self.full_key(key);
+        match self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(&full_key)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) if e.as_service_error().map(|se| se.is_not_found()).unwrap_or(false) => {
+                Ok(false)
+            }
+            Err(e) => Err(AppError::InternalError(format!(
+                "Failed to check S3 object '{full_key}': {e}"
+            ))),
+        }
+    }
+
+    async fn list_prefix(&self, prefix: &str) -> Result<Vec<String>, AppError> {
+        let full_prefix = format!("{}/", self.full_key(prefix).trim_end_matches('/'));
+
+        let resp = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(&full_prefix)
+            .delimiter("/")
+            .send()
+            .await
+            .map_err(|e| AppError::InternalError(format!("Failed to list S3 objects: {e}")))?;
+
+        let mut names: Vec<String> = resp
+            .common_prefixes()
+            .iter()
+            .filter_map(|cp| cp.prefix())
+            .filter_map(|p| p.trim_end_matches('/').rsplit('/').next())
+            // Internal shards like `.blobs` (the content-addressed
+            // staging/dedup tree) aren't a real folder a caller uploaded;
+            // don't surface them via `list_prefix`.
+            .filter(|name| !name.starts_with('.'))
+            .map(|s| s.to_string())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    async fn free_space(&self) -> Result<SpaceInfo, AppError> {
+        // S3-compatible buckets don't expose a meaningful capacity/used
+        // figure the way a mounted disk does, so report "effectively
+        // unlimited" rather than inventing df-style numbers.
+        Ok(SpaceInfo {
+            total: u64::MAX,
+            used: 0,
+            free: u64::MAX,
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), AppError> {
+        let full_key = self.full_key(key);
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(&full_key)
+            .send()
+            .await
+            .map_err(|e| {
+                AppError::InternalError(format!("Failed to delete S3 object '{full_key}': {e}"))
+            })?;
+        Ok(())
+    }
+
+    async fn rename(&self, from: &str, to: &str) -> Result<(), AppError> {
+        // S3 has no native move; emulate it with a server-side copy
+        // followed by a delete of the source.
+        let from_key = self.full_key(from);
+        let to_key = self.full_key(to);
+        self.client
+            .copy_object()
+            .bucket(&self.bucket)
+            .copy_source(format!("{}/{}", self.bucket, from_key))
+            .key(&to_key)
+            .send()
+            .await
+            .map_err(|e| {
+                AppError::InternalError(format!(
+                    "Failed to copy S3 object '{from_key}' to '{to_key}': {e}"
+                ))
+            })?;
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(&from_key)
+            .send()
+            .await
+            .map_err(|e| {
+                AppError::InternalError(format!(
+                    "Failed to delete S3 object '{from_key}' after rename: {e}"
+                ))
+            })?;
+        Ok(())
+    }
+
+    async fn link(&self, canonical: &str, alias: &str) -> Result<String, AppError> {
+        // S3 has no hard links, so the alias is a small pointer object
+        // instead of a real copy of the blob.
+        let alias_key = self.full_key(alias);
+        let pointer = BlobPointer {
+            blob_key: canonical.to_string(),
+        };
+        let json = serde_json::to_vec(&pointer).map_err(|e| {
+            AppError::InternalError(format!("Failed to serialize blob pointer: {e}"))
+        })?;
+
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(&alias_key)
+            .content_type("application/vnd.titanic.blob-pointer+json")
+            .body(aws_sdk_s3::primitives::ByteStream::from(json))
+            .send()
+            .await
+            .map_err(|e| {
+                AppError::InternalError(format!("Failed to write blob pointer '{alias_key}': {e}"))
+            })?;
+
+        Ok(format!("s3://{}/{}", self.bucket, alias_key))
+    }
+
+    async fn open_range(&self, key: &str, range: Option<(u64, u64)>) -> Result<RangeRead, AppError> {
+        let full_key = self.full_key(key);
+
+        let head = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(&full_key)
+            .send()
+            .await
+            .map_err(|e| AppError::NotFound(format!("Clip '{full_key}' not found: {e}")))?;
+
+        // A pointer object stands in for an alias we couldn't hard link;
+        // follow it one level deep to the real blob.
+        if head.content_type() == Some("application/vnd.titanic.blob-pointer+json") {
+            let resp = self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(&full_key)
+                .send()
+                .await
+                .map_err(|e| {
+                    AppError::InternalError(format!("Failed to fetch pointer '{full_key}': {e}"))
+                })?;
+            let bytes = resp.body.collect().await.map_err(|e| {
+                AppError::InternalError(format!("Failed to read pointer '{full_key}': {e}"))
+            })?;
+            let pointer: BlobPointer = serde_json::from_slice(&bytes.into_bytes()).map_err(|e| {
+                AppError::InternalError(format!("Failed to parse pointer '{full_key}': {e}"))
+            })?;
+            // `pointer.blob_key` is already a store-relative key, so this
+            // recurses through `full_key()` again rather than being used
+            // directly.
+            return self.open_range(&pointer.blob_key, range).await;
+        }
+
+        let total_len = head.content_length().unwrap_or(0).max(0) as u64;
+        let last_modified = head
+            .last_modified()
+            .and_then(|t| DateTime::from_timestamp(t.secs(), 0));
+
+        let (start, end) = clamp_range(range, total_len);
+
+        let resp = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(&full_key)
+            .range(format!("bytes={start}-{end}"))
+            .send()
+            .await
+            .map_err(|e| {
+                AppError::InternalError(format!("Failed to fetch S3 object '{full_key}': {e}"))
+            })?;
+
+        Ok(RangeRead {
+            reader: Box::pin(resp.body.into_async_read()),
+            total_len,
+            start,
+            end,
+            last_modified,
+        })
+    }
+}
@@ -0,0 +1,111 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Grace period before sweeping a directory whose owning process we can't
+/// positively confirm is dead (no `/proc` on this platform, or the pid
+/// couldn't be parsed out of the directory name). Long enough that a
+/// normal-speed restart doesn't race a still-shutting-down previous
+/// instance; short enough that a genuinely crashed instance's scratch dir
+/// doesn't linger forever.
+const ORPHAN_GRACE: Duration = Duration::from_secs(60);
+
+/// A per-instance scratch directory under the system temp dir. Staging
+/// files (e.g. the ffprobe scratch file in [`crate::validate`]) are written
+/// here instead of directly under `std::env::temp_dir()`, so that two
+/// instances running on the same host don't collide and a crashed instance
+/// leaves behind one easily-identified directory instead of loose files.
+pub struct TmpDir {
+    path: PathBuf,
+}
+
+impl TmpDir {
+    /// Sweep away any `titanic-*` directories left behind by a previous run
+    /// that didn't exit cleanly, then create a fresh one.
+    pub fn create(base: &Path) -> io::Result<Self> {
+        Self::sweep_orphans(base);
+
+        let nonce = format!(
+            "{}-{}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+        );
+        let path = base.join(format!("titanic-{nonce}"));
+        std::fs::create_dir_all(&path)?;
+        info!("Created instance temp dir at {}", path.display());
+        Ok(TmpDir { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn sweep_orphans(base: &Path) {
+        let entries = match std::fs::read_dir(base) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+            Err(e) => {
+                warn!("Could not scan {} for orphaned temp dirs: {e}", base.display());
+                return;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(rest) = name.strip_prefix("titanic-") else {
+                continue;
+            };
+            let pid = rest.split('-').next().and_then(|p| p.parse::<u32>().ok());
+
+            // A pid we can positively confirm is still running owns this
+            // dir; never touch it, no matter how old it looks (e.g. a
+            // long-lived instance that's just been up for a while).
+            let still_running = match pid {
+                Some(pid) if cfg!(target_os = "linux") => process_is_alive(pid),
+                _ => !is_stale(&entry),
+            };
+            if still_running {
+                continue;
+            }
+
+            let orphan = entry.path();
+            match std::fs::remove_dir_all(&orphan) {
+                Ok(()) => info!("Removed orphaned temp dir {}", orphan.display()),
+                Err(e) => warn!("Failed to remove orphaned temp dir {}: {e}", orphan.display()),
+            }
+        }
+    }
+}
+
+/// Whether `pid` still has a running process, via `/proc/<pid>`.
+#[cfg(target_os = "linux")]
+fn process_is_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_is_alive(_pid: u32) -> bool {
+    false
+}
+
+/// Fallback for platforms (or directory names) where we can't check pid
+/// liveness directly: treat anything younger than [`ORPHAN_GRACE`] as
+/// possibly still in use.
+fn is_stale(entry: &std::fs::DirEntry) -> bool {
+    entry
+        .metadata()
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .map(|age| age > ORPHAN_GRACE)
+        .unwrap_or(true)
+}
+
+impl Drop for TmpDir {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.path) {
+            warn!("Failed to remove instance temp dir {}: {e}", self.path.display());
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use crate::error::AppError;
+use crate::store::ByteStream;
+use bytes::Bytes;
+use futures_util::StreamExt;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{info, warn};
+
+/// `is_valid_video_file` only looks at the filename extension, which
+/// anyone can fake. This buffers enough of the leading bytes to check
+/// known container magic numbers, and optionally falls back to `ffprobe`
+/// when that check is inconclusive, before the upload is handed to a
+/// `Store`.
+pub struct ContentValidator {
+    ffprobe_enabled: bool,
+    ffprobe_timeout: Duration,
+    scratch_dir: PathBuf,
+}
+
+/// Large enough to see a couple of MPEG-TS sync bytes (every 188 bytes)
+/// as well as every other signature, which all live in the first handful
+/// of bytes.
+const SNIFF_LEN: usize = 4 * 188;
+
+/// Formats ffprobe is allowed to report back as "yes, this is a video".
+const KNOWN_FFPROBE_FORMATS: &[&str] = &[
+    "mov", "mp4", "m4v", "3gp", "matroska", "webm", "avi", "flv", "mpegts", "asf", "wmv",
+];
+
+impl ContentValidator {
+    pub fn new(ffprobe_enabled: bool, ffprobe_timeout: Duration, scratch_dir: PathBuf) -> Self {
+        ContentValidator {
+            ffprobe_enabled,
+            ffprobe_timeout,
+            scratch_dir,
+        }
+    }
+
+    /// Confirm `stream` looks like a real video container, returning a
+    /// stream equivalent to the input for the caller to continue
+    /// consuming (the bytes we peeked at are rewound back onto the front).
+    pub async fn validate(&self, stream: ByteStream) -> Result<ByteStream, AppError> {
+        let (prefix, stream) = buffer_prefix(stream, SNIFF_LEN).await?;
+
+        if sniff_container(&prefix) {
+            return Ok(stream);
+        }
+
+        if !self.ffprobe_enabled {
+            warn!("Magic-byte check failed and ffprobe is disabled; rejecting upload");
+            return Err(AppError::UploadError(
+                "File does not look like a recognized video container".to_string(),
+            ));
+        }
+
+        info!("Magic-byte check inconclusive, falling back to ffprobe");
+        self.validate_with_ffprobe(stream).await
+    }
+
+    /// Spool the (ambiguous) stream to a scratch file, ask ffprobe whether
+    /// it recognizes a video format, and if so hand back a fresh stream
+    /// reading from that scratch file (deleting it once fully consumed).
+    async fn validate_with_ffprobe(&self, mut stream: ByteStream) -> Result<ByteStream, AppError> {
+        let scratch_path = self.scratch_dir.join(format!(
+            "probe-{}",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+        ));
+
+        let mut scratch_file = tokio::fs::File::create(&scratch_path).await.map_err(|e| {
+            AppError::InternalError(format!("Failed to create ffprobe scratch file: {e}"))
+        })?;
+
+        // Mirrors `FsStore::save`'s cleanup: a dropped client connection or
+        // the chunk0-5 deadline elapsing mid-spool must not leave this
+        // scratch file behind, so every error path here removes it before
+        // propagating instead of just the "ffprobe said no" branch below.
+        if let Err(e) = spool_to_file(&mut stream, &mut scratch_file).await {
+            let _ = tokio::fs::remove_file(&scratch_path).await;
+            return Err(e);
+        }
+        scratch_file.flush().await.ok();
+        drop(scratch_file);
+
+        let probed = tokio::time::timeout(self.ffprobe_timeout, run_ffprobe(&scratch_path)).await;
+        let recognized = match probed {
+            Ok(Ok(format_name)) => KNOWN_FFPROBE_FORMATS
+                .iter()
+                .any(|known| format_name.contains(known)),
+            Ok(Err(e)) => {
+                warn!("ffprobe failed to read staged upload: {e}");
+                false
+            }
+            Err(_) => {
+                warn!("ffprobe timed out after {:?}", self.ffprobe_timeout);
+                false
+            }
+        };
+
+        if !recognized {
+            let _ = tokio::fs::remove_file(&scratch_path).await;
+            return Err(AppError::UploadError(
+                "File does not look like a recognized video container".to_string(),
+            ));
+        }
+
+        let scratch_file = tokio::fs::File::open(&scratch_path).await.map_err(|e| {
+            AppError::InternalError(format!("Failed to reopen ffprobe scratch file: {e}"))
+        })?;
+        Ok(scratch_file_stream(scratch_file, scratch_path))
+    }
+}
+
+/// Copy the remainder of `stream` into `scratch_file`, stopping at the
+/// first upstream or write error and leaving cleanup of the partial file to
+/// the caller.
+async fn spool_to_file(
+    stream: &mut ByteStream,
+    scratch_file: &mut tokio::fs::File,
+) -> Result<(), AppError> {
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        scratch_file.write_all(&chunk).await.map_err(|e| {
+            AppError::InternalError(format!("Failed to write ffprobe scratch file: {e}"))
+        })?;
+    }
+    Ok(())
+}
+
+/// Buffer up to `want` bytes off the front of `stream`, then return a
+/// stream that replays those buffered chunks before continuing with
+/// whatever was left unconsumed.
+async fn buffer_prefix(
+    mut stream: ByteStream,
+    want: usize,
+) -> Result<(Vec<u8>, ByteStream), AppError> {
+    let mut prefix = Vec::with_capacity(want);
+    let mut buffered: Vec<Bytes> = Vec::new();
+
+    while prefix.len() < want {
+        match stream.next().await {
+            Some(chunk) => {
+                let chunk = chunk?;
+                prefix.extend_from_slice(&chunk);
+                buffered.push(chunk);
+            }
+            None => break,
+        }
+    }
+
+    let rewrapped: ByteStream =
+        Box::pin(futures_util::stream::iter(buffered.into_iter().map(Ok)).chain(stream));
+    Ok((prefix, rewrapped))
+}
+
+fn sniff_container(buf: &[u8]) -> bool {
+    if buf.len() >= 8 && &buf[4..8] == b"ftyp" {
+        return true; // MP4 / MOV / M4V
+    }
+    if buf.len() >= 4 && buf[0..4] == [0x1A, 0x45, 0xDF, 0xA3] {
+        return true; // MKV / WebM (EBML)
+    }
+    if buf.len() >= 12 && &buf[0..4] == b"RIFF" && &buf[8..12] == b"AVI " {
+        return true; // AVI
+    }
+    if buf.len() >= 4 && &buf[0..3] == b"FLV" && buf[3] == 0x01 {
+        return true; // FLV
+    }
+    if buf.len() > 188 && buf.iter().step_by(188).all(|&b| b == 0x47) {
+        return true; // MPEG-TS sync bytes
+    }
+    false
+}
+
+async fn run_ffprobe(path: &std::path::Path) -> Result<String, AppError> {
+    let output = tokio::process::Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=format_name", "-of", "default=nw=1"])
+        .arg(path)
+        .output()
+        .await
+        .map_err(|e| AppError::InternalError(format!("Failed to execute ffprobe: {e}")))?;
+
+    if !output.status.success() {
+        return Err(AppError::UploadError(
+            "ffprobe could not read the uploaded file".to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_lowercase())
+}
+
+/// Stream the contents of `scratch_path` back out, deleting the file once
+/// it has been fully read.
+fn scratch_file_stream(file: tokio::fs::File, scratch_path: PathBuf) -> ByteStream {
+    Box::pin(futures_util::stream::unfold(
+        Some((file, scratch_path)),
+        |state| async move {
+            let (mut file, path) = state?;
+            let mut buf = vec![0u8; 64 * 1024];
+            match file.read(&mut buf).await {
+                Ok(0) => {
+                    let _ = tokio::fs::remove_file(&path).await;
+                    None
+                }
+                Ok(n) => {
+                    buf.truncate(n);
+                    Some((Ok(Bytes::from(buf)), Some((file, path))))
+                }
+                Err(e) => Some((
+                    Err(AppError::InternalError(format!(
+                        "Failed to read ffprobe scratch file: {e}"
+                    ))),
+                    None,
+                )),
+            }
+        },
+    ))
+}